@@ -1,11 +1,38 @@
 use std::io::Result;
 fn main() -> Result<()> {
     // 告诉 cargo 一旦 proto 有变就重新编译
+    println!("cargo:rerun-if-changed=proto/candle.proto");
     println!("cargo:rerun-if-changed=proto/common.proto");
     println!("cargo:rerun-if-changed=proto/dex_trade_event.proto");
+    println!("cargo:rerun-if-changed=proto/mev.proto");
+    println!("cargo:rerun-if-changed=proto/oracle.proto");
+    println!("cargo:rerun-if-changed=proto/pnl.proto");
+    println!("cargo:rerun-if-changed=proto/pool.proto");
+    println!("cargo:rerun-if-changed=proto/pricing.proto");
+    println!("cargo:rerun-if-changed=proto/stats.proto");
+    println!("cargo:rerun-if-changed=proto/sink/kv.proto");
 
-    prost_build::compile_protos(
-        &["proto/common.proto", "proto/dex_trade_event.proto"],
+    // 回放工具与 golden 测试以 JSON 输出 TradeEvent；serde 只在 cli feature 下引入，wasm 构建不依赖它
+    let mut config = prost_build::Config::new();
+    for package in [".io.chainstream.v1.common", ".io.blockchain.v1.dex.trade"] {
+        config.type_attribute(
+            package,
+            "#[cfg_attr(feature = \"cli\", derive(serde::Serialize))]",
+        );
+    }
+    config.compile_protos(
+        &[
+            "proto/candle.proto",
+            "proto/common.proto",
+            "proto/dex_trade_event.proto",
+            "proto/mev.proto",
+            "proto/oracle.proto",
+            "proto/pnl.proto",
+            "proto/pool.proto",
+            "proto/pricing.proto",
+            "proto/stats.proto",
+            "proto/sink/kv.proto",
+        ],
         &["proto/"], // import 搜索路径
     )?;
     println!(
@@ -0,0 +1,72 @@
+-- substreams-sink-sql schema for db_out (PostgreSQL)
+
+CREATE TABLE IF NOT EXISTS trades (
+    event_id                    TEXT        NOT NULL PRIMARY KEY,
+    signature                   TEXT        NOT NULL,
+    instruction_index           INTEGER     NOT NULL,
+    inner_instruction_index     INTEGER     NOT NULL,
+    is_inner_instruction        BOOLEAN     NOT NULL,
+    ordinal                     BIGINT      NOT NULL,
+    slot                        BIGINT      NOT NULL,
+    block_time                  TIMESTAMP   NOT NULL,
+    tx_index                    INTEGER     NOT NULL,
+    pool_address                TEXT        NOT NULL,
+    token_a_address             TEXT        NOT NULL,
+    token_b_address             TEXT        NOT NULL,
+    trader_address              TEXT        NOT NULL,
+    user_a_token_account        TEXT        NOT NULL,
+    user_b_token_account        TEXT        NOT NULL,
+    user_a_amount               NUMERIC,
+    user_b_amount               NUMERIC,
+    was_original_direction      BOOLEAN     NOT NULL,
+    vault_a                     TEXT        NOT NULL,
+    vault_b                     TEXT        NOT NULL,
+    vault_a_post_amount         NUMERIC,
+    vault_b_post_amount         NUMERIC,
+    swap_mode                   TEXT        NOT NULL,
+    amount_specified            NUMERIC,
+    slippage_limit_amount       NUMERIC,
+    realized_amount             NUMERIC,
+    unused_tolerance_bps        BIGINT      NOT NULL,
+    fee                         BIGINT      NOT NULL,
+    priority_fee                BIGINT      NOT NULL,
+    compute_units_consumed      BIGINT      NOT NULL,
+    jito_tip                    BIGINT      NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS trades_pool_slot ON trades (pool_address, slot);
+CREATE INDEX IF NOT EXISTS trades_trader_slot ON trades (trader_address, slot);
+
+CREATE TABLE IF NOT EXISTS pools (
+    address                     TEXT        NOT NULL PRIMARY KEY,
+    program_address             TEXT        NOT NULL,
+    coin_mint                   TEXT        NOT NULL,
+    pc_mint                     TEXT        NOT NULL,
+    coin_decimals               INTEGER     NOT NULL,
+    pc_decimals                 INTEGER     NOT NULL,
+    coin_vault                  TEXT        NOT NULL,
+    pc_vault                    TEXT        NOT NULL,
+    lp_mint                     TEXT        NOT NULL,
+    market_address              TEXT        NOT NULL,
+    first_seen_slot             BIGINT      NOT NULL,
+    source                      TEXT        NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS candles (
+    pool_address                TEXT        NOT NULL,
+    interval                    INTEGER     NOT NULL,
+    start_time                  TIMESTAMP   NOT NULL,
+    end_time                    TIMESTAMP   NOT NULL,
+    base_mint                   TEXT        NOT NULL,
+    quote_mint                  TEXT        NOT NULL,
+    open                        NUMERIC     NOT NULL,
+    high                        NUMERIC     NOT NULL,
+    low                         NUMERIC     NOT NULL,
+    close                       NUMERIC     NOT NULL,
+    base_volume                 NUMERIC     NOT NULL,
+    quote_volume                NUMERIC     NOT NULL,
+    trade_count                 BIGINT      NOT NULL,
+    last_slot                   BIGINT      NOT NULL,
+    closed                      BOOLEAN     NOT NULL,
+    PRIMARY KEY (pool_address, interval, start_time)
+);
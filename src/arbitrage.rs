@@ -0,0 +1,665 @@
+// 单笔交易内的原子套利识别
+//
+// 同一交易中的 Raydium swap 首尾相接构成 A -> B -> ... -> A 的环路即为套利；
+// 若部分腿走了其它 DEX（TradeEvent 中不可见），则以签名者在该交易中的净余额变化判断：
+// Raydium 各腿涉及的 mint 均无净流出，且至少一个 mint 有净流入。
+// 两种判断都至少需要两条腿；签名者的原生 SOL 变化计入 WSOL（临时 WSOL 账户在交易内创建并关闭，
+// 代币余额中看不到），否则用 SOL 直接买入也会被当作无净流出。
+//
+// profit_sol 把各 mint 的净变化按原始单位的兑换比例折算为 lamports：优先取本交易各条腿的池子
+// 在交易后的金库余额，其次取 SOL/USDC 参考池的储备，可沿多个池子传递；任一有变化的 mint
+// 无法折算时留空。
+
+use crate::pb::io::blockchain::v1::dex::mev::{Arbitrage, ArbitrageHop, Arbitrages, MintDelta};
+use crate::pb::io::blockchain::v1::dex::pool::PoolReserves;
+use crate::pb::io::blockchain::v1::dex::trade::{Trade, TradeEvent, TradeEvents};
+use crate::price::{SOL_USDC_POOL, WSOL_MINT};
+use std::collections::BTreeMap;
+use substreams::scalar::BigDecimal;
+use substreams::store::{StoreGet, StoreGetProto};
+use substreams_solana::pb::sf::solana::r#type::v1::{Block, ConfirmedTransaction, TokenBalance};
+
+#[substreams::handlers::map]
+fn map_arbitrages(
+    block: Block,
+    trades: TradeEvents,
+    reserves: StoreGetProto<PoolReserves>,
+) -> Result<Arbitrages, substreams::errors::Error> {
+    Ok(Arbitrages {
+        arbitrages: detect_arbitrages(&block, &trades.events, |ordinal| {
+            reserves.get_at(ordinal, SOL_USDC_POOL)
+        }),
+    })
+}
+
+// sol_reference：参考池在给定 ordinal 处的储备
+fn detect_arbitrages(
+    block: &Block,
+    events: &[TradeEvent],
+    sol_reference: impl Fn(u64) -> Option<PoolReserves>,
+) -> Vec<Arbitrage> {
+    let mut arbitrages: Vec<Arbitrage> = Vec::new();
+
+    // 按交易分组（交易序号即 block.transactions 中的位置）
+    let mut by_tx: BTreeMap<u32, Vec<&TradeEvent>> = BTreeMap::new();
+    for event in events {
+        if event.trade.is_some() {
+            by_tx.entry(event.tx_index()).or_default().push(event);
+        }
+    }
+
+    for (tx_index, mut legs) in by_tx {
+        if legs.len() < 2 {
+            continue;
+        }
+        let Some(tx) = block.transactions.get(tx_index as usize) else {
+            continue;
+        };
+        legs.sort_by_key(|e| e.ordinal);
+
+        let trader = legs[0]
+            .transaction
+            .as_ref()
+            .map(|t| t.signer.clone())
+            .unwrap_or_default();
+        let mut deltas = token_deltas(tx, &trader);
+        *deltas.entry(WSOL_MINT.to_string()).or_default() += lamport_delta(tx, &trader);
+
+        // 每条腿的方向取自该指令自身的 user_source / user_destination
+        let account_mints = account_mints(tx);
+        let mints: Vec<(String, String)> = legs
+            .iter()
+            .map(|leg| leg_mints(leg.trade.as_ref().unwrap(), &account_mints))
+            .collect();
+
+        let raydium_only = is_closed_cycle(&mints);
+        if !raydium_only && !is_net_positive(&mints, &deltas) {
+            continue;
+        }
+
+        let mut cycle: Vec<String> = vec![mints[0].0.clone()];
+        let mut hops: Vec<ArbitrageHop> = Vec::new();
+        for (leg, (mint_in, mint_out)) in legs.iter().zip(&mints) {
+            let trade = leg.trade.as_ref().unwrap();
+            if cycle.last() != Some(mint_in) {
+                cycle.push(mint_in.clone());
+            }
+            cycle.push(mint_out.clone());
+            hops.push(ArbitrageHop {
+                instruction: leg.instruction.clone(),
+                pool_address: trade.pool_address.clone(),
+                mint_in: mint_in.clone(),
+                mint_out: mint_out.clone(),
+                amount_in: trade.user_a_amount.clone(),
+                amount_out: trade.user_b_amount.clone(),
+            });
+        }
+        if cycle.last() != cycle.first() {
+            cycle.push(cycle[0].clone());
+        }
+
+        let rates = lamport_rates(&legs, sol_reference(legs[0].ordinal));
+        let profit_sol = deltas
+            .iter()
+            .filter(|(_, amount)| **amount != 0)
+            .try_fold(BigDecimal::zero(), |total, (mint, amount)| {
+                Some(total + decimal(*amount) * rates.get(mint)?.clone())
+            })
+            .map(|lamports| lamports.to_bigint().to_string())
+            .unwrap_or_default();
+
+        arbitrages.push(Arbitrage {
+            block: legs[0].block.clone(),
+            transaction: legs[0].transaction.clone(),
+            trader_address: trader,
+            cycle,
+            pools: hops.iter().map(|h| h.pool_address.clone()).collect(),
+            hops,
+            raydium_only,
+            net_profit: deltas
+                .iter()
+                .filter(|(_, amount)| **amount != 0)
+                .map(|(mint, amount)| MintDelta {
+                    mint: mint.clone(),
+                    amount: amount.to_string(),
+                })
+                .collect(),
+            profit_sol,
+        });
+    }
+
+    arbitrages
+}
+
+// 所有腿首尾相接，且最后买入的 mint 等于最初卖出的 mint
+fn is_closed_cycle(mints: &[(String, String)]) -> bool {
+    if mints.len() < 2 {
+        return false;
+    }
+    let chained = mints.windows(2).all(|w| w[0].1 == w[1].0);
+    chained && mints.last().unwrap().1 == mints[0].0
+}
+
+// Raydium 各腿涉及的 mint 对签名者均无净流出，且至少一个有净流入
+fn is_net_positive(mints: &[(String, String)], deltas: &BTreeMap<String, i128>) -> bool {
+    if mints.len() < 2 {
+        return false;
+    }
+    let mut any_gain = false;
+    for (sold, bought) in mints {
+        for mint in [sold, bought] {
+            let delta = deltas.get(mint).copied().unwrap_or_default();
+            if delta < 0 {
+                return false;
+            }
+            any_gain |= delta > 0;
+        }
+    }
+    any_gain
+}
+
+// 单条腿的 (卖出 mint, 买入 mint)：按指令中 user_source / user_destination 账户的代币余额确定，
+// 账户没有余额条目（如交易内创建又关闭的临时 WSOL 账户）时回退为 Trade 中的方向
+fn leg_mints(trade: &Trade, account_mints: &BTreeMap<String, String>) -> (String, String) {
+    let mint_of = |account: &str, fallback: &str| {
+        account_mints
+            .get(account)
+            .cloned()
+            .unwrap_or_else(|| fallback.to_string())
+    };
+    (
+        mint_of(&trade.user_a_token_account_address, trade.sold_mint()),
+        mint_of(&trade.user_b_token_account_address, trade.bought_mint()),
+    )
+}
+
+// 交易内代币账户地址 → mint（账户索引按含 ALT 的完整账户表解析）
+fn account_mints(tx: &ConfirmedTransaction) -> BTreeMap<String, String> {
+    let mut mints: BTreeMap<String, String> = BTreeMap::new();
+    let Some(meta) = tx.meta.as_ref() else {
+        return mints;
+    };
+    if tx
+        .transaction
+        .as_ref()
+        .and_then(|t| t.message.as_ref())
+        .is_none()
+    {
+        return mints;
+    }
+    let keys = tx.resolved_accounts();
+    for b in meta
+        .pre_token_balances
+        .iter()
+        .chain(&meta.post_token_balances)
+    {
+        if let Some(key) = keys.get(b.account_index as usize) {
+            mints
+                .entry(bs58::encode(key).into_string())
+                .or_insert_with(|| b.mint.clone());
+        }
+    }
+    mints
+}
+
+// owner 名下所有代币账户的净变化（post - pre），按 mint 汇总
+fn token_deltas(tx: &ConfirmedTransaction, owner: &str) -> BTreeMap<String, i128> {
+    let mut deltas: BTreeMap<String, i128> = BTreeMap::new();
+    let Some(meta) = tx.meta.as_ref() else {
+        return deltas;
+    };
+    let amount = |b: &TokenBalance| -> i128 {
+        b.ui_token_amount
+            .as_ref()
+            .and_then(|u| u.amount.parse::<i128>().ok())
+            .unwrap_or(0)
+    };
+    for b in meta.pre_token_balances.iter().filter(|b| b.owner == owner) {
+        *deltas.entry(b.mint.clone()).or_default() -= amount(b);
+    }
+    for b in meta.post_token_balances.iter().filter(|b| b.owner == owner) {
+        *deltas.entry(b.mint.clone()).or_default() += amount(b);
+    }
+    deltas
+}
+
+// 每个 mint 一个原始单位折合的 lamports：WSOL 为 1，其余沿池子的储备比例传递
+fn lamport_rates(
+    legs: &[&TradeEvent],
+    sol_reference: Option<PoolReserves>,
+) -> BTreeMap<String, BigDecimal> {
+    // (mint x, mint y, x 的储备, y 的储备)
+    let mut pairs: Vec<(String, String, u128, u128)> = Vec::new();
+    for leg in legs {
+        let trade = leg.trade.as_ref().unwrap();
+        let (a, b) = (&trade.token_a_address, &trade.token_b_address);
+        if let (Some(reserve_a), Some(reserve_b)) =
+            (trade.vault_post_amount(a), trade.vault_post_amount(b))
+        {
+            pairs.push((a.clone(), b.clone(), reserve_a, reserve_b));
+        }
+    }
+    if let Some(r) = sol_reference {
+        if let (Ok(coin), Ok(pc)) = (r.coin_reserve.parse(), r.pc_reserve.parse()) {
+            pairs.push((r.coin_mint, r.pc_mint, coin, pc));
+        }
+    }
+
+    let mut rates: BTreeMap<String, BigDecimal> = BTreeMap::new();
+    rates.insert(WSOL_MINT.to_string(), BigDecimal::one());
+    loop {
+        let mut changed = false;
+        for (x, y, reserve_x, reserve_y) in &pairs {
+            if *reserve_x == 0 || *reserve_y == 0 {
+                continue;
+            }
+            for (known, unknown, reserve_known, reserve_unknown) in
+                [(x, y, reserve_x, reserve_y), (y, x, reserve_y, reserve_x)]
+            {
+                if rates.contains_key(unknown) {
+                    continue;
+                }
+                if let Some(rate) = rates.get(known) {
+                    let rate = rate.clone() * decimal(*reserve_known) / decimal(*reserve_unknown);
+                    rates.insert(unknown.clone(), rate);
+                    changed = true;
+                }
+            }
+        }
+        if !changed {
+            return rates;
+        }
+    }
+}
+
+fn decimal(amount: impl ToString) -> BigDecimal {
+    BigDecimal::try_from(amount.to_string()).unwrap_or_default()
+}
+
+// 签名者原生 SOL 余额变化（已扣除手续费与 Jito 小费），按 WSOL 计。
+// 交易内新建（pre 为 0）或关闭（post 为 0）的其它账户的 lamports 视为签名者支付或收回的租金，
+// 不计入利润；WSOL 账户中包装的 SOL 不属于租金（已计入代币余额变化）
+fn lamport_delta(tx: &ConfirmedTransaction, trader: &str) -> i128 {
+    let Some(meta) = tx.meta.as_ref() else {
+        return 0;
+    };
+    if tx
+        .transaction
+        .as_ref()
+        .and_then(|t| t.message.as_ref())
+        .is_none()
+    {
+        return 0;
+    }
+    let keys = tx.resolved_accounts();
+    let Some(trader_idx) = keys
+        .iter()
+        .position(|k| bs58::encode(k).into_string() == trader)
+    else {
+        return 0;
+    };
+    let wrapped = |balances: &[TokenBalance], idx: usize| -> i128 {
+        balances
+            .iter()
+            .find(|b| b.account_index as usize == idx && b.mint == WSOL_MINT)
+            .and_then(|b| b.ui_token_amount.as_ref())
+            .and_then(|u| u.amount.parse::<i128>().ok())
+            .unwrap_or(0)
+    };
+
+    let mut delta: i128 = 0;
+    for (idx, (&pre, &post)) in meta
+        .pre_balances
+        .iter()
+        .zip(&meta.post_balances)
+        .enumerate()
+    {
+        let (pre, post) = (pre as i128, post as i128);
+        if idx == trader_idx {
+            delta += post - pre;
+        } else if pre == 0 && post > 0 {
+            delta += post - wrapped(&meta.post_token_balances, idx);
+        } else if pre > 0 && post == 0 {
+            delta -= pre - wrapped(&meta.pre_token_balances, idx);
+        }
+    }
+    delta
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::price::USDC_MINT;
+    use crate::raydium::RayLog;
+    use crate::testing::{
+        pubkey, ray_log, signature, swap_base_in_data, BlockBuilder, SwapAccountsSpec,
+        TransactionBuilder,
+    };
+    use crate::{extract_trade_events, RAYDIUM};
+    use std::collections::HashMap;
+
+    const DECIMALS: u32 = 6;
+
+    // 一条 Raydium 腿：(amm, coin 金库, pc 金库, user_source, user_destination, 付出, 得到)
+    struct Leg<'a>(&'a str, &'a str, &'a str, &'a str, &'a str, u64, u64);
+
+    // 聚合器顶层指令下依次 CPI 调用各条 Raydium 腿
+    fn route(trader: &str, legs: &[Leg]) -> TransactionBuilder {
+        let aggregator = pubkey(11);
+        let mut tx = TransactionBuilder::new(&signature(1), trader)
+            .instruction(&aggregator, &[], vec![])
+            .log(&format!("Program {} invoke [1]", aggregator));
+        for Leg(amm, coin_vault, pc_vault, source, destination, paid, received) in legs {
+            let accounts = SwapAccountsSpec {
+                amm,
+                amm_authority: &pubkey(13),
+                pool_coin_token_account: coin_vault,
+                pool_pc_token_account: pc_vault,
+                user_source: source,
+                user_destination: destination,
+                user_owner: trader,
+            }
+            .to_accounts();
+            tx = tx
+                .inner_instruction(0, RAYDIUM, &accounts, swap_base_in_data(*paid, 0))
+                .log(&format!("Program {} invoke [2]", RAYDIUM))
+                .log(&ray_log(RayLog::SwapBaseIn {
+                    amount_in: *paid,
+                    minimum_out: 0,
+                    direction: 0,
+                    user_source: 0,
+                    pool_coin: 0,
+                    pool_pc: 0,
+                    out_amount: *received,
+                }))
+                .log(&format!("Program {} success", RAYDIUM));
+        }
+        tx.log(&format!("Program {} success", aggregator))
+    }
+
+    fn vault(tx: TransactionBuilder, account: &str, mint: &str) -> TransactionBuilder {
+        vault_reserve(tx, account, mint, 1_000_000)
+    }
+
+    fn vault_reserve(
+        tx: TransactionBuilder,
+        account: &str,
+        mint: &str,
+        reserve: u64,
+    ) -> TransactionBuilder {
+        tx.token_balance(
+            account,
+            mint,
+            &pubkey(13),
+            DECIMALS,
+            Some(reserve),
+            Some(reserve),
+        )
+    }
+
+    fn detect(tx: TransactionBuilder) -> Vec<Arbitrage> {
+        detect_with_reference(tx, None)
+    }
+
+    fn detect_with_reference(
+        tx: TransactionBuilder,
+        reference: Option<PoolReserves>,
+    ) -> Vec<Arbitrage> {
+        let block = BlockBuilder::new(300).transaction(tx).build();
+        let events = extract_trade_events(&block, &HashMap::new());
+        detect_arbitrages(&block, &events, |_| reference.clone())
+    }
+
+    #[test]
+    fn round_trip_cycle_is_arbitrage() {
+        let (trader, user_coin, user_pc) = (pubkey(10), pubkey(16), pubkey(17));
+        let (coin, pc) = (pubkey(18), pubkey(19));
+        let (amm_1, amm_2) = (pubkey(30), pubkey(40));
+        // coin → pc（池 1）→ coin（池 2），卖出 1000 coin 换回 1100 coin
+        let tx = route(
+            &trader,
+            &[
+                Leg(
+                    &amm_1,
+                    &pubkey(31),
+                    &pubkey(32),
+                    &user_coin,
+                    &user_pc,
+                    1_000,
+                    2_000,
+                ),
+                Leg(
+                    &amm_2,
+                    &pubkey(41),
+                    &pubkey(42),
+                    &user_pc,
+                    &user_coin,
+                    2_000,
+                    1_100,
+                ),
+            ],
+        )
+        .token_balance(
+            &user_coin,
+            &coin,
+            &trader,
+            DECIMALS,
+            Some(5_000),
+            Some(5_100),
+        )
+        .token_balance(&user_pc, &pc, &trader, DECIMALS, Some(0), Some(0));
+        let tx = vault(tx, &pubkey(31), &coin);
+        let tx = vault(tx, &pubkey(32), &pc);
+        let tx = vault(tx, &pubkey(41), &coin);
+        let tx = vault(tx, &pubkey(42), &pc);
+
+        let arbitrages = detect(tx);
+        assert_eq!(arbitrages.len(), 1);
+        let arbitrage = &arbitrages[0];
+        assert!(arbitrage.raydium_only);
+        assert_eq!(
+            arbitrage.cycle,
+            vec![coin.clone(), pc.clone(), coin.clone()]
+        );
+        assert_eq!(arbitrage.pools, vec![amm_1, amm_2]);
+        assert_eq!(arbitrage.hops[1].mint_in, pc);
+        assert_eq!(arbitrage.hops[1].amount_out, "1100");
+        assert_eq!(
+            arbitrage.net_profit,
+            vec![MintDelta {
+                mint: coin,
+                amount: "100".to_string(),
+            }]
+        );
+        assert_eq!(arbitrage.trader_address, trader);
+        // coin 与 pc 都没有通往 WSOL 的池子，无法折算
+        assert_eq!(arbitrage.profit_sol, "");
+    }
+
+    #[test]
+    fn profit_is_converted_with_leg_pool_price() {
+        let (trader, user_usdc, user_wsol, new_account) =
+            (pubkey(10), pubkey(16), pubkey(17), pubkey(50));
+        // USDC → WSOL（池 1）→ USDC（池 2），多得 100 USDC；池 1 交易后 1 USDC 单位折合 2 lamports
+        let tx = route(
+            &trader,
+            &[
+                Leg(
+                    &pubkey(30),
+                    &pubkey(31),
+                    &pubkey(32),
+                    &user_usdc,
+                    &user_wsol,
+                    1_000,
+                    520,
+                ),
+                Leg(
+                    &pubkey(40),
+                    &pubkey(41),
+                    &pubkey(42),
+                    &user_wsol,
+                    &user_usdc,
+                    520,
+                    1_100,
+                ),
+            ],
+        )
+        .token_balance(
+            &user_usdc,
+            USDC_MINT,
+            &trader,
+            DECIMALS,
+            Some(5_000),
+            Some(5_100),
+        )
+        .token_balance(&user_wsol, WSOL_MINT, &trader, 9, Some(0), Some(0))
+        // 手续费 5000 lamports，另为新账户支付 2_039_280 lamports 租金
+        .lamports(&trader, 10_000_000, 7_955_720)
+        .lamports(&new_account, 0, 2_039_280);
+        let tx = vault_reserve(tx, &pubkey(31), WSOL_MINT, 2_000_000);
+        let tx = vault_reserve(tx, &pubkey(32), USDC_MINT, 1_000_000);
+        let tx = vault(tx, &pubkey(41), WSOL_MINT);
+        let tx = vault(tx, &pubkey(42), USDC_MINT);
+
+        let arbitrages = detect(tx);
+        assert_eq!(arbitrages.len(), 1);
+        let arbitrage = &arbitrages[0];
+        assert!(arbitrage.raydium_only);
+        assert_eq!(
+            arbitrage.net_profit,
+            vec![
+                MintDelta {
+                    mint: USDC_MINT.to_string(),
+                    amount: "100".to_string(),
+                },
+                MintDelta {
+                    mint: WSOL_MINT.to_string(),
+                    amount: "-5000".to_string(),
+                },
+            ]
+        );
+        // 100 USDC × 2 - 5000 手续费；租金不计入
+        assert_eq!(arbitrage.profit_sol, "-4800");
+    }
+
+    #[test]
+    fn profit_is_converted_through_sol_reference() {
+        let (trader, user_coin, user_usdc) = (pubkey(10), pubkey(16), pubkey(17));
+        let coin = pubkey(18);
+        // coin → USDC → coin，多得 100 coin；coin 经池 1 换算为 USDC，USDC 经参考池换算为 SOL
+        let tx = route(
+            &trader,
+            &[
+                Leg(
+                    &pubkey(30),
+                    &pubkey(31),
+                    &pubkey(32),
+                    &user_coin,
+                    &user_usdc,
+                    1_000,
+                    2_000,
+                ),
+                Leg(
+                    &pubkey(40),
+                    &pubkey(41),
+                    &pubkey(42),
+                    &user_usdc,
+                    &user_coin,
+                    2_000,
+                    1_100,
+                ),
+            ],
+        )
+        .token_balance(
+            &user_coin,
+            &coin,
+            &trader,
+            DECIMALS,
+            Some(5_000),
+            Some(5_100),
+        )
+        .token_balance(&user_usdc, USDC_MINT, &trader, DECIMALS, Some(0), Some(0));
+        let tx = vault(tx, &pubkey(31), &coin);
+        let tx = vault(tx, &pubkey(32), USDC_MINT);
+        let tx = vault(tx, &pubkey(41), &coin);
+        let tx = vault(tx, &pubkey(42), USDC_MINT);
+
+        // 参考池：3_000_000 lamports 对 1_000_000 USDC 单位
+        let reference = PoolReserves {
+            pool_address: SOL_USDC_POOL.to_string(),
+            coin_mint: WSOL_MINT.to_string(),
+            pc_mint: USDC_MINT.to_string(),
+            coin_reserve: "3000000".to_string(),
+            pc_reserve: "1000000".to_string(),
+            ..Default::default()
+        };
+        let arbitrages = detect_with_reference(tx, Some(reference));
+        assert_eq!(arbitrages.len(), 1);
+        assert_eq!(arbitrages[0].profit_sol, "300");
+    }
+
+    #[test]
+    fn plain_sol_buy_is_not_arbitrage() {
+        let (trader, temp_wsol, user_token) = (pubkey(10), pubkey(16), pubkey(17));
+        let token = pubkey(19);
+        // 临时 WSOL 账户在交易内创建并关闭，没有代币余额条目；SOL 从签名者的 lamports 中付出
+        let tx = route(
+            &trader,
+            &[Leg(
+                &pubkey(30),
+                &pubkey(31),
+                &pubkey(32),
+                &temp_wsol,
+                &user_token,
+                1_000_000,
+                500,
+            )],
+        )
+        .token_balance(&user_token, &token, &trader, DECIMALS, Some(0), Some(500))
+        .lamports(&trader, 10_000_000, 8_995_000);
+        let tx = vault(tx, &pubkey(31), WSOL_MINT);
+        let tx = vault(tx, &pubkey(32), &token);
+
+        assert!(detect(tx).is_empty());
+    }
+
+    #[test]
+    fn two_hop_sol_buy_is_not_arbitrage() {
+        let (trader, temp_wsol, user_mid, user_token) =
+            (pubkey(10), pubkey(16), pubkey(17), pubkey(20));
+        let (mid, token) = (pubkey(19), pubkey(21));
+        // SOL → mid → token：中间代币净变动为 0，只有买入的代币增加，SOL 减少记在 lamports 上
+        let tx = route(
+            &trader,
+            &[
+                Leg(
+                    &pubkey(30),
+                    &pubkey(31),
+                    &pubkey(32),
+                    &temp_wsol,
+                    &user_mid,
+                    1_000_000,
+                    700,
+                ),
+                Leg(
+                    &pubkey(40),
+                    &pubkey(41),
+                    &pubkey(42),
+                    &user_mid,
+                    &user_token,
+                    700,
+                    500,
+                ),
+            ],
+        )
+        .token_balance(&user_mid, &mid, &trader, DECIMALS, Some(0), Some(0))
+        .token_balance(&user_token, &token, &trader, DECIMALS, Some(0), Some(500))
+        .lamports(&trader, 10_000_000, 8_995_000);
+        let tx = vault(tx, &pubkey(31), WSOL_MINT);
+        let tx = vault(tx, &pubkey(32), &mid);
+        let tx = vault(tx, &pubkey(41), &mid);
+        let tx = vault(tx, &pubkey(42), &token);
+
+        assert!(detect(tx).is_empty());
+    }
+}
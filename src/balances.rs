@@ -0,0 +1,154 @@
+// 代币余额变动解析：按账户索引查询 pre/post token balance
+
+use std::collections::BTreeMap;
+use substreams_solana::pb::sf::solana::r#type::v1::{TokenBalance, TransactionStatusMeta};
+
+// 一个代币账户在交易内的变动；数量均为原始 amount
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BalanceChange {
+    pub account_index: u32,
+    // 字符串字段与类型化字段一致，未知时为空字符串
+    // |post - pre|
+    pub amount: String,
+    pub pre_amount: String,
+    pub post_amount: String,
+    // 类型化数量：交易内关闭的账户没有 post 条目、新建的账户没有 pre 条目，缺失的一侧为 0；
+    // 两侧都没有条目或无法解析时为 None，delta 只在 pre/post 都已知时给出
+    pub pre: Option<u64>,
+    pub post: Option<u64>,
+    pub delta: Option<u64>,
+}
+
+pub struct TokenBalances<'a> {
+    pre: BTreeMap<u32, &'a TokenBalance>,
+    post: BTreeMap<u32, &'a TokenBalance>,
+}
+
+impl<'a> TokenBalances<'a> {
+    pub fn new(meta: &'a TransactionStatusMeta) -> Self {
+        let index = |balances: &'a [TokenBalance]| -> BTreeMap<u32, &'a TokenBalance> {
+            balances.iter().map(|b| (b.account_index, b)).collect()
+        };
+        TokenBalances {
+            pre: index(&meta.pre_token_balances),
+            post: index(&meta.post_token_balances),
+        }
+    }
+
+    pub fn pre_amount(&self, account_index: u32) -> Option<i128> {
+        amount(self.pre.get(&account_index))
+    }
+
+    pub fn post_amount(&self, account_index: u32) -> Option<i128> {
+        amount(self.post.get(&account_index))
+    }
+
+    // 缺失的一侧按 0 计
+    pub fn delta(&self, account_index: u32) -> i128 {
+        self.post_amount(account_index).unwrap_or(0) - self.pre_amount(account_index).unwrap_or(0)
+    }
+
+    // 优先取 pre，账户在交易内创建时取 post
+    pub fn mint(&self, account_index: u32) -> Option<&'a str> {
+        self.get(account_index).map(|b| b.mint.as_str())
+    }
+
+    pub fn owner(&self, account_index: u32) -> Option<&'a str> {
+        self.get(account_index).map(|b| b.owner.as_str())
+    }
+
+    pub fn change(&self, account_index: u32) -> BalanceChange {
+        let (pre_entry, post_entry) = (self.pre.get(&account_index), self.post.get(&account_index));
+        let side = |entry: Option<&&TokenBalance>| match entry {
+            Some(_) => amount(entry).and_then(|a| u64::try_from(a).ok()),
+            None if pre_entry.is_some() || post_entry.is_some() => Some(0),
+            None => None,
+        };
+        let (pre, post) = (side(pre_entry), side(post_entry));
+        let delta = pre.zip(post).map(|(pre, post)| post.abs_diff(pre));
+        let string = |amount: Option<u64>| amount.map(|a| a.to_string()).unwrap_or_default();
+        BalanceChange {
+            account_index,
+            amount: string(delta),
+            pre_amount: string(pre),
+            post_amount: string(post),
+            pre,
+            post,
+            delta,
+        }
+    }
+
+    fn get(&self, account_index: u32) -> Option<&'a TokenBalance> {
+        self.pre
+            .get(&account_index)
+            .or_else(|| self.post.get(&account_index))
+            .copied()
+    }
+}
+
+fn amount(balance: Option<&&TokenBalance>) -> Option<i128> {
+    balance
+        .and_then(|b| b.ui_token_amount.as_ref())
+        .and_then(|u| u.amount.parse::<i128>().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{pubkey, signature, TransactionBuilder};
+
+    #[test]
+    fn closed_account_counts_post_as_zero() {
+        let (trader, user_coin, user_pc) = (pubkey(10), pubkey(16), pubkey(17));
+        // 卖出侧账户在交易内被关闭：只有 pre 条目
+        let tx = TransactionBuilder::new(&signature(1), &trader)
+            .token_balance(&user_coin, &pubkey(18), &trader, 6, Some(1_000), None)
+            .token_balance(&user_pc, &pubkey(19), &trader, 6, None, Some(2_000))
+            .build();
+        let balances = TokenBalances::new(tx.meta.as_ref().unwrap());
+
+        assert_eq!(balances.delta(1), -1_000);
+        assert_eq!(balances.delta(2), 2_000);
+
+        // 关闭的账户 post 为已知的 0，字符串字段与类型化字段一致
+        let change = balances.change(1);
+        assert_eq!(change.amount, "1000");
+        assert_eq!(change.post_amount, "0");
+        assert_eq!(
+            (change.pre, change.post, change.delta),
+            (Some(1_000), Some(0), Some(1_000))
+        );
+        // 新建的账户 pre 为 0
+        let change = balances.change(2);
+        assert_eq!((change.pre_amount.as_str(), change.pre), ("0", Some(0)));
+        // 没有任何条目的账户：全部未知
+        let change = balances.change(3);
+        assert_eq!(change.amount, "");
+        assert_eq!((change.pre_amount.as_str(), change.post), ("", None));
+    }
+
+    #[test]
+    fn alt_loaded_accounts_use_resolved_indexes() {
+        let (trader, authority, user_coin, user_pc, vault) =
+            (pubkey(10), pubkey(13), pubkey(16), pubkey(17), pubkey(14));
+        // 账户表：[trader, vault, user_coin(writable), user_pc(readonly)]
+        let tx = TransactionBuilder::new(&signature(1), &trader)
+            .lookup_writable(&user_coin)
+            .lookup_readonly(&user_pc)
+            .token_balance(&vault, &pubkey(18), &authority, 6, Some(10), Some(11))
+            .token_balance(&user_coin, &pubkey(18), &trader, 6, Some(5), Some(4))
+            .token_balance(&user_pc, &pubkey(19), &trader, 6, Some(0), Some(2))
+            .build();
+        let keys: Vec<String> = tx
+            .resolved_accounts()
+            .iter()
+            .map(|k| bs58::encode(k).into_string())
+            .collect();
+        let balances = TokenBalances::new(tx.meta.as_ref().unwrap());
+
+        let index = |account: &str| keys.iter().position(|k| k == account).unwrap() as u32;
+        assert_eq!(balances.delta(index(&user_coin)), -1);
+        assert_eq!(balances.delta(index(&user_pc)), 2);
+        assert_eq!(balances.owner(index(&vault)), Some(authority.as_str()));
+    }
+}
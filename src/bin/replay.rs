@@ -0,0 +1,127 @@
+// 本地回放：读取录制的区块文件，执行与 map_ray_swap 相同的提取逻辑，按行输出 TradeEvent 的 JSON
+//
+// 用法：replay <blocks-file> [--slot <slot>] [--signature <sig>] [--output <file.jsonl>] [--dump <file.bin>]
+//
+// --dump 把选中的区块以长度前缀格式写出，用于从 merged-blocks 中裁剪测试 fixture；
+// 同时指定 --signature 时区块内只保留该笔交易（transaction.index 随之变为 0）
+
+use prost::Message;
+use sol_ray_swap::pb::io::blockchain::v1::dex::trade::TradeEvents;
+use sol_ray_swap::{read_blocks, Replay};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::process::ExitCode;
+
+struct Args {
+    input: String,
+    slot: Option<u64>,
+    signature: Option<String>,
+    output: Option<String>,
+    dump: Option<String>,
+}
+
+const USAGE: &str = "usage: replay <blocks-file> [--slot <slot>] [--signature <sig>] \
+[--output <file.jsonl>] [--dump <file.bin>]";
+
+fn parse_args() -> Result<Args, String> {
+    let mut input: Option<String> = None;
+    let mut slot: Option<u64> = None;
+    let mut signature: Option<String> = None;
+    let mut output: Option<String> = None;
+    let mut dump: Option<String> = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--slot" => {
+                let value = args.next().ok_or("--slot requires a value")?;
+                slot = Some(
+                    value
+                        .parse()
+                        .map_err(|_| format!("invalid slot: {}", value))?,
+                );
+            }
+            "--signature" => {
+                signature = Some(args.next().ok_or("--signature requires a value")?);
+            }
+            "--output" | "-o" => {
+                output = Some(args.next().ok_or("--output requires a value")?);
+            }
+            "--dump" => {
+                dump = Some(args.next().ok_or("--dump requires a value")?);
+            }
+            "--help" | "-h" => return Err(USAGE.to_string()),
+            _ if input.is_none() => input = Some(arg),
+            _ => return Err(format!("unexpected argument: {}", arg)),
+        }
+    }
+
+    Ok(Args {
+        input: input.ok_or(USAGE)?,
+        slot,
+        signature,
+        output,
+        dump,
+    })
+}
+
+fn run(args: Args) -> Result<(), String> {
+    let bytes = std::fs::read(&args.input).map_err(|e| format!("{}: {}", args.input, e))?;
+    let mut blocks = read_blocks(&bytes).map_err(|e| format!("{}: {}", args.input, e))?;
+
+    let mut out: Box<dyn Write> = match args.output.as_ref() {
+        Some(path) => Box::new(BufWriter::new(
+            File::create(path).map_err(|e| format!("{}: {}", path, e))?,
+        )),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+
+    let mut dumped: Vec<u8> = Vec::new();
+
+    // 所有区块都要经过 Replay 以登记池子，--slot / --signature 只过滤输出
+    let mut replay = Replay::new();
+    for block in blocks.iter_mut() {
+        if args.slot.is_some_and(|slot| slot != block.slot) {
+            replay.process(block);
+            continue;
+        }
+        // 裁剪到单笔交易前先用完整区块登记池子
+        if let Some(signature) = args.signature.as_ref() {
+            replay.register_pools(block);
+            block.transactions.retain(|tx| tx.id() == *signature);
+            if block.transactions.is_empty() {
+                continue;
+            }
+        }
+        write_events(&mut out, &replay.process(block))?;
+        if args.dump.is_some() {
+            block
+                .encode_length_delimited(&mut dumped)
+                .map_err(|e| e.to_string())?;
+        }
+    }
+    out.flush().map_err(|e| e.to_string())?;
+
+    if let Some(path) = args.dump.as_ref() {
+        std::fs::write(path, dumped).map_err(|e| format!("{}: {}", path, e))?;
+    }
+    Ok(())
+}
+
+fn write_events(out: &mut dyn Write, events: &TradeEvents) -> Result<(), String> {
+    for event in &events.events {
+        let line = serde_json::to_string(event).map_err(|e| e.to_string())?;
+        writeln!(out, "{}", line).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    match parse_args().and_then(run) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,429 @@
+// OHLCV K 线：按周期聚合每个池子的成交价与成交量
+//
+// store 无法读取自身状态，因此拆成按更新策略划分的多个 store：
+// map_candle_updates 先在区块内聚合出局部 K 线，open / high / low / close / volume
+// 分别写入 set_if_not_exists / max / min / set / add 的 store，store_open_candles 记录每个
+// (周期, 池子) 当前所在的 K 线；map_candles 再从这些 store 拼出完整的 K 线。
+// 同一区块内所有交易共享 block_time，因此每个区块对每个 (周期, 池子) 至多更新一根 K 线。
+//
+// 各 OHLCV store 同时读取 store_open_candles 的 deltas：某个 (周期, 池子) 换到新 K 线时删除上一根的 key，
+// 否则 key 会随时间无限增长。删除发生在换线所在的区块，map_candles 以 get_first 读取上一根，
+// 得到的是区块开始时（删除之前）的值。
+
+use std::collections::BTreeMap;
+
+use crate::pb::io::blockchain::v1::dex::candle::{Candle, Candles};
+use crate::pb::io::blockchain::v1::dex::pool::Pool;
+use crate::pb::io::blockchain::v1::dex::trade::{TradeEvent, TradeEvents};
+use crate::{params, price};
+use substreams::pb::substreams::store_delta::Operation;
+use substreams::scalar::BigDecimal;
+use substreams::store::{
+    DeltaProto, Deltas, StoreAdd, StoreAddBigDecimal, StoreDelete, StoreGet, StoreGetBigDecimal,
+    StoreGetProto, StoreMax, StoreMaxBigDecimal, StoreMin, StoreMinBigDecimal, StoreNew, StoreSet,
+    StoreSetBigDecimal, StoreSetIfNotExists, StoreSetIfNotExistsBigDecimal, StoreSetProto,
+};
+
+// 未配置 params 时的默认周期：1m / 5m / 1h / 1d
+const DEFAULT_INTERVALS: &[u32] = &[60, 300, 3_600, 86_400];
+
+#[substreams::handlers::map]
+fn map_candle_updates(
+    params: String,
+    trades: TradeEvents,
+    pools: StoreGetProto<Pool>,
+) -> Result<Candles, substreams::errors::Error> {
+    let intervals = params::parse_durations(&params, DEFAULT_INTERVALS)?;
+    Ok(Candles {
+        candles: aggregate_candles(&trades.events, &intervals, |address| {
+            pools.get_last(address)
+        }),
+    })
+}
+
+// 按 ordinal 顺序把本区块的成交聚合为每个 (周期, 池子) 的局部 K 线
+fn aggregate_candles(
+    events: &[TradeEvent],
+    intervals: &[u32],
+    pool: impl Fn(&str) -> Option<Pool>,
+) -> Vec<Candle> {
+    let mut events: Vec<&TradeEvent> = events.iter().collect();
+    events.sort_by_key(|e| e.ordinal);
+
+    let mut bars: BTreeMap<(u32, String), Bar> = BTreeMap::new();
+    for event in events {
+        let (Some(trade), Some(block)) = (event.trade.as_ref(), event.block.as_ref()) else {
+            continue;
+        };
+        let Some(pool) = pool(&trade.pool_address) else {
+            continue;
+        };
+        let Some(tp) = price::trade_price(trade, &pool) else {
+            continue;
+        };
+
+        for &interval in intervals {
+            let start = block.timestamp - block.timestamp.rem_euclid(interval as i64);
+            bars.entry((interval, pool.address.clone()))
+                .and_modify(|bar| bar.apply(&tp, block.slot))
+                .or_insert_with(|| Bar::new(&pool, interval, start, &tp, block.slot));
+        }
+    }
+
+    bars.into_values().map(Bar::into_candle).collect()
+}
+
+#[substreams::handlers::store]
+fn store_candle_open(
+    updates: Candles,
+    open_candles: Deltas<DeltaProto<Candle>>,
+    store: StoreSetIfNotExistsBigDecimal,
+) {
+    prune_closed_candles(&open_candles, &store);
+    for c in &updates.candles {
+        store.set_if_not_exists(0, candle_key(c), &decimal(&c.open));
+    }
+}
+
+#[substreams::handlers::store]
+fn store_candle_high(
+    updates: Candles,
+    open_candles: Deltas<DeltaProto<Candle>>,
+    store: StoreMaxBigDecimal,
+) {
+    prune_closed_candles(&open_candles, &store);
+    for c in &updates.candles {
+        store.max(0, candle_key(c), decimal(&c.high));
+    }
+}
+
+#[substreams::handlers::store]
+fn store_candle_low(
+    updates: Candles,
+    open_candles: Deltas<DeltaProto<Candle>>,
+    store: StoreMinBigDecimal,
+) {
+    prune_closed_candles(&open_candles, &store);
+    for c in &updates.candles {
+        store.min(0, candle_key(c), decimal(&c.low));
+    }
+}
+
+#[substreams::handlers::store]
+fn store_candle_close(
+    updates: Candles,
+    open_candles: Deltas<DeltaProto<Candle>>,
+    store: StoreSetBigDecimal,
+) {
+    prune_closed_candles(&open_candles, &store);
+    for c in &updates.candles {
+        store.set(0, candle_key(c), &decimal(&c.close));
+    }
+}
+
+// 每根 K 线三个 key：<candle>:base、<candle>:quote、<candle>:trades
+#[substreams::handlers::store]
+fn store_candle_volume(
+    updates: Candles,
+    open_candles: Deltas<DeltaProto<Candle>>,
+    store: StoreAddBigDecimal,
+) {
+    prune_closed_candles(&open_candles, &store);
+    for c in &updates.candles {
+        let key = candle_key(c);
+        store.add(0, format!("{}:base", key), decimal(&c.base_volume));
+        store.add(0, format!("{}:quote", key), decimal(&c.quote_volume));
+        store.add(
+            0,
+            format!("{}:trades", key),
+            BigDecimal::from(c.trade_count),
+        );
+    }
+}
+
+// 上一根 K 线的全部 key（volume 的 :base / :quote / :trades 同在此前缀下）
+fn prune_closed_candles(open_candles: &Deltas<DeltaProto<Candle>>, store: &impl StoreDelete) {
+    for delta in open_candles.iter() {
+        if delta.operation == Operation::Update
+            && delta.old_value.start_time != delta.new_value.start_time
+        {
+            store.delete_prefix(0, &candle_key(&delta.old_value));
+        }
+    }
+}
+
+// (周期, 池子) -> 当前 K 线（只用到其标识字段与 last_slot）
+#[substreams::handlers::store]
+fn store_open_candles(updates: Candles, store: StoreSetProto<Candle>) {
+    for c in &updates.candles {
+        store.set(0, series_key(c.interval, &c.pool_address), c);
+    }
+}
+
+// 输出本区块更新过的 K 线；某个 (周期, 池子) 进入新 K 线时，同时输出上一根并标记 closed
+#[substreams::handlers::map]
+fn map_candles(
+    updates: Candles,
+    open: StoreGetBigDecimal,
+    high: StoreGetBigDecimal,
+    low: StoreGetBigDecimal,
+    close: StoreGetBigDecimal,
+    volume: StoreGetBigDecimal,
+    open_candles: StoreGetProto<Candle>,
+) -> Result<Candles, substreams::errors::Error> {
+    let stores = CandleStores {
+        open,
+        high,
+        low,
+        close,
+        volume,
+    };
+    let mut candles = Vec::new();
+
+    for update in &updates.candles {
+        if let Some(previous) =
+            open_candles.get_first(series_key(update.interval, &update.pool_address))
+        {
+            if previous.start_time != update.start_time {
+                candles.push(stores.load(&previous, true));
+            }
+        }
+        candles.push(stores.load(update, false));
+    }
+
+    Ok(Candles { candles })
+}
+
+struct CandleStores {
+    open: StoreGetBigDecimal,
+    high: StoreGetBigDecimal,
+    low: StoreGetBigDecimal,
+    close: StoreGetBigDecimal,
+    volume: StoreGetBigDecimal,
+}
+
+impl CandleStores {
+    // 以 update 的标识字段为准，OHLCV 取各 store 的最新值；
+    // 已收盘的 K 线在本区块被删除，取区块开始时的值
+    fn load(&self, update: &Candle, closed: bool) -> Candle {
+        let key = candle_key(update);
+        let get = |store: &StoreGetBigDecimal, key: &str| {
+            let value = if closed {
+                store.get_first(key)
+            } else {
+                store.get_last(key)
+            };
+            value.unwrap_or_default().to_string()
+        };
+        Candle {
+            open: get(&self.open, &key),
+            high: get(&self.high, &key),
+            low: get(&self.low, &key),
+            close: get(&self.close, &key),
+            base_volume: get(&self.volume, &format!("{}:base", key)),
+            quote_volume: get(&self.volume, &format!("{}:quote", key)),
+            trade_count: get(&self.volume, &format!("{}:trades", key))
+                .parse()
+                .unwrap_or_default(),
+            closed,
+            ..update.clone()
+        }
+    }
+}
+
+// 区块内的局部 K 线
+struct Bar {
+    pool_address: String,
+    base_mint: String,
+    quote_mint: String,
+    interval: u32,
+    start_time: i64,
+    open: BigDecimal,
+    high: BigDecimal,
+    low: BigDecimal,
+    close: BigDecimal,
+    base_volume: BigDecimal,
+    quote_volume: BigDecimal,
+    trade_count: u64,
+    last_slot: u64,
+}
+
+impl Bar {
+    fn new(pool: &Pool, interval: u32, start_time: i64, tp: &price::TradePrice, slot: u64) -> Bar {
+        Bar {
+            pool_address: pool.address.clone(),
+            base_mint: pool.coin_mint.clone(),
+            quote_mint: pool.pc_mint.clone(),
+            interval,
+            start_time,
+            open: tp.price.clone(),
+            high: tp.price.clone(),
+            low: tp.price.clone(),
+            close: tp.price.clone(),
+            base_volume: tp.base_volume.clone(),
+            quote_volume: tp.quote_volume.clone(),
+            trade_count: 1,
+            last_slot: slot,
+        }
+    }
+
+    fn apply(&mut self, tp: &price::TradePrice, slot: u64) {
+        if tp.price > self.high {
+            self.high = tp.price.clone();
+        }
+        if tp.price < self.low {
+            self.low = tp.price.clone();
+        }
+        self.close = tp.price.clone();
+        self.base_volume = self.base_volume.clone() + tp.base_volume.clone();
+        self.quote_volume = self.quote_volume.clone() + tp.quote_volume.clone();
+        self.trade_count += 1;
+        self.last_slot = slot;
+    }
+
+    fn into_candle(self) -> Candle {
+        Candle {
+            pool_address: self.pool_address,
+            base_mint: self.base_mint,
+            quote_mint: self.quote_mint,
+            interval: self.interval,
+            start_time: self.start_time,
+            end_time: self.start_time + self.interval as i64,
+            open: self.open.to_string(),
+            high: self.high.to_string(),
+            low: self.low.to_string(),
+            close: self.close.to_string(),
+            base_volume: self.base_volume.to_string(),
+            quote_volume: self.quote_volume.to_string(),
+            trade_count: self.trade_count,
+            last_slot: self.last_slot,
+            closed: false,
+        }
+    }
+}
+
+fn candle_key(c: &Candle) -> String {
+    format!(
+        "{}:{}",
+        series_key(c.interval, &c.pool_address),
+        c.start_time
+    )
+}
+
+fn series_key(interval: u32, pool_address: &str) -> String {
+    format!("{}:{}", interval, pool_address)
+}
+
+fn decimal(s: &str) -> BigDecimal {
+    BigDecimal::try_from(s).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pb::io::blockchain::v1::dex::trade::{Trade, TradeAmounts};
+    use crate::pb::io::chainstream::v1::common::Block;
+
+    const POOL: &str = "pool";
+    const COIN: &str = "coin";
+    const PC: &str = "pc";
+
+    fn pool(address: &str) -> Option<Pool> {
+        (address == POOL).then(|| Pool {
+            address: POOL.to_string(),
+            coin_mint: COIN.to_string(),
+            pc_mint: PC.to_string(),
+            coin_decimals: 0,
+            pc_decimals: 0,
+            ..Default::default()
+        })
+    }
+
+    // 卖出 coin 数量 coin、买入 pc 数量 pc，成交价 pc / coin
+    fn trade(ordinal: u64, slot: u64, timestamp: i64, coin: u64, pc: u64) -> TradeEvent {
+        TradeEvent {
+            ordinal,
+            block: Some(Block {
+                timestamp,
+                slot,
+                ..Default::default()
+            }),
+            trade: Some(Trade {
+                pool_address: POOL.to_string(),
+                token_a_address: COIN.to_string(),
+                token_b_address: PC.to_string(),
+                was_original_direction: true,
+                amounts: Some(TradeAmounts {
+                    user_a_amount: Some(coin),
+                    user_b_amount: Some(pc),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn trades_are_aggregated_in_ordinal_order() {
+        // 乱序输入：按 ordinal 排序后价格依次为 2、5、1、3
+        let candles = aggregate_candles(
+            &[
+                trade(3, 10, 125, 10, 10),
+                trade(1, 10, 125, 10, 20),
+                trade(4, 10, 125, 10, 30),
+                trade(2, 10, 125, 10, 50),
+            ],
+            &[60],
+            pool,
+        );
+        assert_eq!(candles.len(), 1);
+        let c = &candles[0];
+        assert_eq!(c.pool_address, POOL);
+        assert_eq!(c.base_mint, COIN);
+        assert_eq!(c.quote_mint, PC);
+        assert_eq!((c.start_time, c.end_time), (120, 180));
+        assert_eq!(decimal(&c.open), BigDecimal::from(2));
+        assert_eq!(decimal(&c.high), BigDecimal::from(5));
+        assert_eq!(decimal(&c.low), BigDecimal::from(1));
+        assert_eq!(decimal(&c.close), BigDecimal::from(3));
+        assert_eq!(c.base_volume, "40");
+        assert_eq!(c.quote_volume, "110");
+        assert_eq!(c.trade_count, 4);
+        assert_eq!(c.last_slot, 10);
+        assert!(!c.closed);
+    }
+
+    #[test]
+    fn each_interval_gets_its_own_bar() {
+        let candles = aggregate_candles(&[trade(1, 10, 3_725, 10, 20)], &[60, 3_600], pool);
+        let starts: Vec<(u32, i64)> = candles.iter().map(|c| (c.interval, c.start_time)).collect();
+        assert_eq!(starts, vec![(60, 3_720), (3_600, 3_600)]);
+    }
+
+    #[test]
+    fn negative_timestamp_rounds_down() {
+        let candles = aggregate_candles(&[trade(1, 10, -1, 10, 20)], &[60], pool);
+        assert_eq!(candles[0].start_time, -60);
+    }
+
+    #[test]
+    fn unknown_pool_and_unpriced_trades_are_skipped() {
+        let mut other = trade(1, 10, 0, 10, 20);
+        other.trade.as_mut().unwrap().pool_address = "other".to_string();
+        let zero = trade(2, 10, 0, 0, 20);
+        assert!(aggregate_candles(&[other, zero], &[60], pool).is_empty());
+    }
+
+    #[test]
+    fn keys_identify_series_and_bar() {
+        assert_eq!(series_key(300, POOL), "300:pool");
+        let candle = Candle {
+            interval: 300,
+            pool_address: POOL.to_string(),
+            start_time: 1_200,
+            ..Default::default()
+        };
+        assert_eq!(candle_key(&candle), "300:pool:1200");
+    }
+}
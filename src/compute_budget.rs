@@ -0,0 +1,162 @@
+// ComputeBudget 程序指令解析与优先费计算
+
+use substreams_solana::pb::sf::solana::r#type::v1::Message;
+
+pub const COMPUTE_BUDGET: &str = "ComputeBudget111111111111111111111111111111";
+
+const SET_COMPUTE_UNIT_LIMIT: u8 = 2;
+const SET_COMPUTE_UNIT_PRICE: u8 = 3;
+
+// 未设置 SetComputeUnitLimit 时，每条非 ComputeBudget 顶层指令默认 200k CU，整笔上限 1.4M
+const DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT: u64 = 200_000;
+const MAX_COMPUTE_UNIT_LIMIT: u64 = 1_400_000;
+const MICRO_LAMPORTS_PER_LAMPORT: u128 = 1_000_000;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ComputeBudget {
+    pub unit_limit: Option<u32>,
+    // micro-lamports / CU
+    pub unit_price: Option<u64>,
+    // 实际生效的 CU 上限
+    pub effective_limit: u64,
+}
+
+impl ComputeBudget {
+    pub fn from_message(message: &Message, account_keys: &[String]) -> ComputeBudget {
+        let mut budget = ComputeBudget::default();
+        let mut other_instructions: u64 = 0;
+
+        for ix in &message.instructions {
+            let is_compute_budget = account_keys
+                .get(ix.program_id_index as usize)
+                .map(|k| k == COMPUTE_BUDGET)
+                .unwrap_or(false);
+            if !is_compute_budget {
+                other_instructions += 1;
+                continue;
+            }
+            let Some((&tag, rest)) = ix.data.split_first() else {
+                continue;
+            };
+            match tag {
+                SET_COMPUTE_UNIT_LIMIT => {
+                    if let Some(units) = rest.get(..4).and_then(|b| b.try_into().ok()) {
+                        budget.unit_limit = Some(u32::from_le_bytes(units));
+                    }
+                }
+                SET_COMPUTE_UNIT_PRICE => {
+                    if let Some(price) = rest.get(..8).and_then(|b| b.try_into().ok()) {
+                        budget.unit_price = Some(u64::from_le_bytes(price));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        budget.effective_limit = match budget.unit_limit {
+            Some(limit) => limit as u64,
+            None => other_instructions * DEFAULT_INSTRUCTION_COMPUTE_UNIT_LIMIT,
+        }
+        .min(MAX_COMPUTE_UNIT_LIMIT);
+
+        budget
+    }
+
+    // 优先费（lamports）= ceil(price * limit / 1e6)，按申请的 CU 上限而非实际消耗计费
+    pub fn priority_fee(&self) -> u64 {
+        let price = self.unit_price.unwrap_or_default() as u128;
+        let micro_lamports = price * self.effective_limit as u128;
+        micro_lamports.div_ceil(MICRO_LAMPORTS_PER_LAMPORT) as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{pubkey, signature, TransactionBuilder};
+
+    fn limit(units: u32) -> Vec<u8> {
+        let mut data = vec![SET_COMPUTE_UNIT_LIMIT];
+        data.extend_from_slice(&units.to_le_bytes());
+        data
+    }
+
+    fn price(micro_lamports: u64) -> Vec<u8> {
+        let mut data = vec![SET_COMPUTE_UNIT_PRICE];
+        data.extend_from_slice(&micro_lamports.to_le_bytes());
+        data
+    }
+
+    fn budget(tx: TransactionBuilder) -> ComputeBudget {
+        let tx = tx.build();
+        let account_keys: Vec<String> = tx
+            .resolved_accounts()
+            .iter()
+            .map(|k| bs58::encode(k).into_string())
+            .collect();
+        let message = tx.transaction.unwrap().message.unwrap();
+        ComputeBudget::from_message(&message, &account_keys)
+    }
+
+    fn tx() -> TransactionBuilder {
+        TransactionBuilder::new(&signature(1), &pubkey(10))
+    }
+
+    #[test]
+    fn priority_fee_rounds_up() {
+        // 150_001 CU × 3 micro-lamports = 450_003 micro-lamports → 1 lamport
+        let b = budget(
+            tx().instruction(COMPUTE_BUDGET, &[], limit(150_001))
+                .instruction(COMPUTE_BUDGET, &[], price(3))
+                .instruction(&pubkey(20), &[], vec![]),
+        );
+        assert_eq!(b.unit_limit, Some(150_001));
+        assert_eq!(b.unit_price, Some(3));
+        assert_eq!(b.effective_limit, 150_001);
+        assert_eq!(b.priority_fee(), 1);
+
+        // 300_000 CU × 50_000 micro-lamports = 15_000 lamports，整除时不进位
+        let b = budget(
+            tx().instruction(COMPUTE_BUDGET, &[], limit(300_000))
+                .instruction(COMPUTE_BUDGET, &[], price(50_000)),
+        );
+        assert_eq!(b.priority_fee(), 15_000);
+    }
+
+    #[test]
+    fn default_limit_is_per_non_budget_instruction() {
+        // 两条普通指令 + 一条 SetComputeUnitPrice：2 × 200k
+        let b = budget(
+            tx().instruction(COMPUTE_BUDGET, &[], price(1_000_000))
+                .instruction(&pubkey(20), &[], vec![])
+                .instruction(&pubkey(21), &[], vec![]),
+        );
+        assert_eq!(b.unit_limit, None);
+        assert_eq!(b.effective_limit, 400_000);
+        assert_eq!(b.priority_fee(), 400_000);
+    }
+
+    #[test]
+    fn limit_is_capped() {
+        let b = budget(tx().instruction(COMPUTE_BUDGET, &[], limit(2_000_000)));
+        assert_eq!(b.effective_limit, MAX_COMPUTE_UNIT_LIMIT);
+
+        // 8 条普通指令默认 1.6M，同样截到 1.4M
+        let mut many = tx();
+        for seed in 20..28 {
+            many = many.instruction(&pubkey(seed), &[], vec![]);
+        }
+        assert_eq!(budget(many).effective_limit, MAX_COMPUTE_UNIT_LIMIT);
+    }
+
+    #[test]
+    fn missing_price_has_no_priority_fee() {
+        let b = budget(
+            tx().instruction(COMPUTE_BUDGET, &[], limit(300_000))
+                .instruction(&pubkey(20), &[], vec![]),
+        );
+        assert_eq!(b.unit_price, None);
+        assert_eq!(b.effective_limit, 300_000);
+        assert_eq!(b.priority_fee(), 0);
+    }
+}
@@ -0,0 +1,131 @@
+// substreams-sink-sql 输出：trades / pools / candles 三张表，表结构见 schema.sql
+
+use crate::pb::io::blockchain::v1::dex::candle::Candles;
+use crate::pb::io::blockchain::v1::dex::pool::{Pool, PoolSource};
+use crate::pb::io::blockchain::v1::dex::trade::{SwapMode, TradeEvents};
+use substreams::store::{DeltaProto, Deltas};
+use substreams_database_change::pb::database::DatabaseChanges;
+use substreams_database_change::tables::{Row, Tables};
+
+#[substreams::handlers::map]
+fn db_out(
+    trades: TradeEvents,
+    pools: Deltas<DeltaProto<Pool>>,
+    candles: Candles,
+) -> Result<DatabaseChanges, substreams::errors::Error> {
+    let mut tables = Tables::new();
+
+    for event in &trades.events {
+        let (Some(trade), Some(block), Some(tx), Some(ix)) = (
+            event.trade.as_ref(),
+            event.block.as_ref(),
+            event.transaction.as_ref(),
+            event.instruction.as_ref(),
+        ) else {
+            continue;
+        };
+
+        let row = tables.create_row("trades", &event.event_id);
+        row.set("signature", &tx.signature)
+            .set("instruction_index", ix.index)
+            .set("inner_instruction_index", ix.inner_instruction_index)
+            .set("is_inner_instruction", ix.is_inner_instruction)
+            .set("ordinal", event.ordinal)
+            .set("slot", block.slot)
+            .set("block_time", timestamp(block.timestamp))
+            .set("tx_index", tx.index)
+            .set("pool_address", &trade.pool_address)
+            .set("token_a_address", &trade.token_a_address)
+            .set("token_b_address", &trade.token_b_address)
+            .set("trader_address", trade.trader())
+            .set("user_a_token_account", &trade.user_a_token_account_address)
+            .set("user_b_token_account", &trade.user_b_token_account_address)
+            .set("was_original_direction", trade.was_original_direction)
+            .set("vault_a", &trade.vault_a)
+            .set("vault_b", &trade.vault_b)
+            .set(
+                "swap_mode",
+                SwapMode::try_from(trade.swap_mode)
+                    .unwrap_or_default()
+                    .as_str_name(),
+            )
+            .set("unused_tolerance_bps", trade.unused_tolerance_bps)
+            .set("fee", tx.fee)
+            .set("priority_fee", tx.priority_fee)
+            .set("compute_units_consumed", ix.compute_units_consumed)
+            .set("jito_tip", tx.jito_tip);
+        set_numeric(row, "user_a_amount", &trade.user_a_amount);
+        set_numeric(row, "user_b_amount", &trade.user_b_amount);
+        set_numeric(row, "vault_a_post_amount", &trade.vault_a_post_amount);
+        set_numeric(row, "vault_b_post_amount", &trade.vault_b_post_amount);
+        set_numeric(row, "amount_specified", &trade.amount_specified);
+        set_numeric(row, "slippage_limit_amount", &trade.slippage_limit_amount);
+        set_numeric(row, "realized_amount", &trade.realized_amount);
+    }
+
+    // store_pools 为 set_if_not_exists，deltas 只包含首次登记的池子
+    for delta in pools.iter() {
+        let pool = &delta.new_value;
+        tables
+            .create_row("pools", &pool.address)
+            .set("program_address", &pool.program_address)
+            .set("coin_mint", &pool.coin_mint)
+            .set("pc_mint", &pool.pc_mint)
+            .set("coin_decimals", pool.coin_decimals)
+            .set("pc_decimals", pool.pc_decimals)
+            .set("coin_vault", &pool.coin_vault)
+            .set("pc_vault", &pool.pc_vault)
+            .set("lp_mint", &pool.lp_mint)
+            .set("market_address", &pool.market_address)
+            .set("first_seen_slot", pool.first_seen_slot)
+            .set(
+                "source",
+                PoolSource::try_from(pool.source)
+                    .unwrap_or_default()
+                    .as_str_name(),
+            );
+    }
+
+    // K 线会在后续区块继续更新，使用 upsert
+    for candle in &candles.candles {
+        tables
+            .upsert_row(
+                "candles",
+                [
+                    ("pool_address", candle.pool_address.clone()),
+                    ("interval", candle.interval.to_string()),
+                    ("start_time", timestamp(candle.start_time).to_string()),
+                ],
+            )
+            .set("end_time", timestamp(candle.end_time))
+            .set("base_mint", &candle.base_mint)
+            .set("quote_mint", &candle.quote_mint)
+            .set("open", &candle.open)
+            .set("high", &candle.high)
+            .set("low", &candle.low)
+            .set("close", &candle.close)
+            .set("base_volume", &candle.base_volume)
+            .set("quote_volume", &candle.quote_volume)
+            .set("trade_count", candle.trade_count)
+            .set("last_slot", candle.last_slot)
+            .set("closed", candle.closed);
+    }
+
+    let mut changes = tables.to_database_changes();
+    // 行已按写入顺序排列；列来自 HashMap，排序以保证输出稳定
+    for change in &mut changes.table_changes {
+        change.fields.sort_by(|a, b| a.name.cmp(&b.name));
+    }
+    Ok(changes)
+}
+
+fn timestamp(seconds: i64) -> prost_types::Timestamp {
+    prost_types::Timestamp { seconds, nanos: 0 }
+}
+
+// 数量字段可能为空（解析失败），空值写 NULL
+fn set_numeric(row: &mut Row, name: &str, value: &str) {
+    if !value.is_empty() {
+        row.set(name, value);
+    }
+}
@@ -0,0 +1,102 @@
+// TradeEvent 的通用信封：Block / Transaction / Instruction / DApp（io.chainstream.v1.common）
+
+use crate::compute_budget::ComputeBudget;
+use crate::jito;
+use crate::pb::io::chainstream::v1::common::{
+    Block as CBlock, Chain, DApp as CDApp, Instruction as CInstruction, Status,
+    Transaction as CTransaction,
+};
+use substreams_solana::pb::sf::solana::r#type::v1::{Block, ConfirmedTransaction};
+
+// Block 信息（尽力从 Solana Block 中映射；缺失字段使用默认值）
+pub fn block_info(block: &Block) -> CBlock {
+    CBlock {
+        timestamp: block
+            .block_time
+            .as_ref()
+            .map(|t| t.timestamp)
+            .unwrap_or_default(),
+        hash: block.blockhash.clone(),
+        height: block
+            .block_height
+            .as_ref()
+            .map(|h| h.block_height)
+            .unwrap_or_default(),
+        slot: block.slot,
+    }
+}
+
+// 交易信息：签名、费用、ComputeBudget 与 Jito 小费；tx_index 为交易在区块内的原始位置
+pub fn transaction_info(tx: &ConfirmedTransaction, tx_index: u32) -> CTransaction {
+    let meta = tx.meta.as_ref();
+    let message = tx.transaction.as_ref().and_then(|t| t.message.as_ref());
+
+    let signature = tx
+        .transaction
+        .as_ref()
+        .and_then(|t| t.signatures.first())
+        .map(|sig| bs58::encode(sig).into_string())
+        .unwrap_or_default();
+
+    // 费支付者/签名者（通常为第一个账户）
+    let static_keys: Vec<String> = message
+        .map(|m| {
+            m.account_keys
+                .iter()
+                .map(|k| bs58::encode(k).into_string())
+                .collect()
+        })
+        .unwrap_or_default();
+    let fee_payer = static_keys.first().cloned().unwrap_or_default();
+
+    // ComputeBudget 指令（程序地址不会出现在 ALT 中，静态账户表即可）
+    let budget = message
+        .map(|m| ComputeBudget::from_message(m, &static_keys))
+        .unwrap_or_default();
+    // Jito 小费（区分 bundle 内的 MEV 流量与普通流量）
+    let tip = jito::detect_tip(tx).unwrap_or_default();
+
+    CTransaction {
+        fee: meta.map(|m| m.fee).unwrap_or_default(),
+        fee_payer: fee_payer.clone(),
+        index: tx_index,
+        signature,
+        signer: fee_payer,
+        status: if meta.is_some_and(|m| m.err.is_none()) {
+            Status::Success as i32
+        } else {
+            Status::Failed as i32
+        },
+        compute_units_consumed: meta
+            .and_then(|m| m.compute_units_consumed)
+            .unwrap_or_default(),
+        compute_unit_limit: budget.unit_limit.unwrap_or_default(),
+        compute_unit_price: budget.unit_price.unwrap_or_default(),
+        priority_fee: budget.priority_fee(),
+        jito_tip: tip.lamports,
+        jito_tip_account: tip.account,
+    }
+}
+
+pub fn instruction_info(
+    index: u32,
+    inner_index: Option<u32>,
+    r#type: &str,
+    compute_units_consumed: u64,
+) -> CInstruction {
+    CInstruction {
+        index,
+        is_inner_instruction: inner_index.is_some(),
+        inner_instruction_index: inner_index.unwrap_or_default(),
+        r#type: r#type.to_string(),
+        compute_units_consumed,
+    }
+}
+
+pub fn dapp(program_address: &str) -> CDApp {
+    CDApp {
+        program_address: program_address.to_string(),
+        inner_program_address: program_address.to_string(),
+        chain: Chain::Solana as i32,
+    }
+}
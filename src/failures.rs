@@ -0,0 +1,242 @@
+// 失败交易中的 Raydium swap 尝试：附带解码后的错误与指令限额，用于统计各池子的失败率
+
+use crate::envelope::{self, block_info};
+use crate::logs::{self, InvocationTree};
+use crate::pb::io::blockchain::v1::dex::trade::{ProgramError, SwapFailure, SwapFailures};
+use crate::raydium::{self, SwapAccounts};
+use crate::{instructions, tx_error, RAYDIUM};
+use substreams_solana::pb::sf::solana::r#type::v1::{Block, Message, TransactionError};
+
+#[substreams::handlers::map]
+fn map_ray_swap_failures(block: Block) -> Result<SwapFailures, substreams::errors::Error> {
+    Ok(SwapFailures {
+        failures: extract_swap_failures(&block),
+    })
+}
+
+fn extract_swap_failures(block: &Block) -> Vec<SwapFailure> {
+    let mut failures: Vec<SwapFailure> = Vec::new();
+    let c_block = block_info(block);
+
+    // block.transactions() 只返回成功交易，这里需要遍历全部交易
+    for (tx_index, tx) in block.transactions.iter().enumerate() {
+        let Some(meta) = tx.meta.as_ref() else {
+            continue;
+        };
+        let Some(err) = meta.err.as_ref() else {
+            continue;
+        };
+        let Some(message) = tx.transaction.as_ref().and_then(|t| t.message.as_ref()) else {
+            continue;
+        };
+
+        // 账户表（含 v0 交易通过 ALT 加载的地址）
+        let account_keys: Vec<String> = tx
+            .resolved_accounts()
+            .iter()
+            .map(|k| bs58::encode(k).into_string())
+            .collect();
+
+        let instructions = instructions::flatten(message, meta);
+
+        let mut error: Option<ProgramError> = None;
+        let invocations = InvocationTree::parse(
+            &meta.log_messages,
+            &logs::top_level_programs(message, &account_keys),
+        );
+        let c_tx = envelope::transaction_info(tx, tx_index as u32);
+
+        for ix in instructions {
+            if account_keys
+                .get(ix.program_id_index as usize)
+                .map(String::as_str)
+                != Some(RAYDIUM)
+            {
+                continue;
+            }
+            let Some(swap_ix) = raydium::decode_swap(ix.data) else {
+                continue;
+            };
+            let accounts: Vec<String> = ix
+                .accounts
+                .iter()
+                .filter_map(|&acc_idx| account_keys.get(acc_idx as usize).cloned())
+                .collect();
+            // 账户布局无法识别时没有池子地址，不输出
+            let Some(swap_accounts) = SwapAccounts::from_accounts(&accounts) else {
+                continue;
+            };
+
+            let error = error
+                .get_or_insert_with(|| {
+                    program_error(err, &meta.log_messages, &account_keys, message)
+                })
+                .clone();
+
+            failures.push(SwapFailure {
+                instruction: Some(envelope::instruction_info(
+                    ix.index,
+                    ix.inner_index,
+                    "RaydiumSwap",
+                    invocations
+                        .get(ix.index, ix.inner_index)
+                        .and_then(|inv| inv.consumed_units)
+                        .unwrap_or_default(),
+                )),
+                block: Some(c_block.clone()),
+                transaction: Some(c_tx.clone()),
+                d_app: Some(envelope::dapp(RAYDIUM)),
+                pool_address: swap_accounts.amm,
+                user_source_token_account_address: swap_accounts.user_source,
+                user_destination_token_account_address: swap_accounts.user_destination,
+                user_owner_address: swap_accounts.user_owner,
+                swap_mode: swap_ix.mode() as i32,
+                amount_specified: swap_ix.amount_specified().to_string(),
+                slippage_limit_amount: swap_ix.slippage_limit().to_string(),
+                // 交易级错误没有指令序号，不归属任何 swap
+                in_failed_instruction: error.instruction_index == Some(ix.index),
+                error: Some(error),
+            });
+        }
+    }
+
+    failures
+}
+
+// 合并 meta.err 与失败日志：日志给出最内层抛错的程序（CPI 场景下不是顶层程序），
+// meta.err 给出失败的顶层指令序号
+fn program_error(
+    err: &TransactionError,
+    logs: &[String],
+    account_keys: &[String],
+    message: &Message,
+) -> ProgramError {
+    let mut error = ProgramError::default();
+
+    if let Some(failure) = tx_error::decode_instruction_error(err) {
+        error.instruction_index = Some(failure.instruction_index);
+        error.name = failure.kind;
+        if let Some(code) = failure.custom_code {
+            error.is_custom = true;
+            error.custom_code = code;
+        }
+        error.program_address = message
+            .instructions
+            .get(failure.instruction_index as usize)
+            .and_then(|ix| account_keys.get(ix.program_id_index as usize))
+            .cloned()
+            .unwrap_or_default();
+    }
+
+    if let Some(failed_log) = tx_error::first_failed_log(logs) {
+        error.program_address = failed_log.program;
+        error.log_message = failed_log.message;
+        if let Some(code) = failed_log.custom_code {
+            error.is_custom = true;
+            error.custom_code = code;
+        }
+    }
+
+    if error.is_custom {
+        error.name = if error.program_address == RAYDIUM {
+            raydium::amm_error_name(error.custom_code).unwrap_or("Custom")
+        } else {
+            "Custom"
+        }
+        .to_string();
+    }
+
+    error
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{
+        pubkey, signature, swap_base_in_data, BlockBuilder, SwapAccountsSpec, TransactionBuilder,
+    };
+
+    // InstructionError(index, Custom(30))
+    fn slippage_error(index: u8) -> Vec<u8> {
+        vec![8, 0, 0, 0, index, 25, 0, 0, 0, 30, 0, 0, 0]
+    }
+
+    fn swap_accounts(amm: &str) -> Vec<String> {
+        SwapAccountsSpec {
+            amm,
+            amm_authority: &pubkey(13),
+            pool_coin_token_account: &pubkey(14),
+            pool_pc_token_account: &pubkey(15),
+            user_source: &pubkey(16),
+            user_destination: &pubkey(17),
+            user_owner: &pubkey(10),
+        }
+        .to_accounts()
+    }
+
+    fn failed_swap(amm: &str) -> TransactionBuilder {
+        TransactionBuilder::new(&signature(1), &pubkey(10))
+            .instruction(RAYDIUM, &swap_accounts(amm), swap_base_in_data(1_000, 990))
+            .log(&format!("Program {} invoke [1]", RAYDIUM))
+            .log(&format!(
+                "Program {} failed: custom program error: 0x1e",
+                RAYDIUM
+            ))
+    }
+
+    fn extract(tx: TransactionBuilder) -> Vec<SwapFailure> {
+        extract_swap_failures(&BlockBuilder::new(300).transaction(tx).build())
+    }
+
+    #[test]
+    fn failed_swap_is_decoded() {
+        let amm = pubkey(30);
+        let failures = extract(failed_swap(&amm).error(slippage_error(0)));
+
+        assert_eq!(failures.len(), 1);
+        let failure = &failures[0];
+        assert_eq!(failure.pool_address, amm);
+        assert_eq!(failure.user_source_token_account_address, pubkey(16));
+        assert_eq!(failure.amount_specified, "1000");
+        assert_eq!(failure.slippage_limit_amount, "990");
+        assert!(failure.in_failed_instruction);
+
+        let error = failure.error.as_ref().unwrap();
+        assert_eq!(error.instruction_index, Some(0));
+        assert_eq!(error.program_address, RAYDIUM);
+        assert!(error.is_custom);
+        assert_eq!(error.custom_code, 0x1e);
+        assert_eq!(error.name, "ExceededSlippage");
+    }
+
+    #[test]
+    fn swap_outside_failed_instruction_is_not_flagged() {
+        let failures = extract(failed_swap(&pubkey(30)).error(slippage_error(1)));
+        assert_eq!(
+            failures[0].error.as_ref().unwrap().instruction_index,
+            Some(1)
+        );
+        assert!(!failures[0].in_failed_instruction);
+    }
+
+    #[test]
+    fn transaction_level_error_has_no_instruction_index() {
+        // InsufficientFundsForFee：没有指令序号，顶层序号 0 的 swap 不应被标记
+        let failures = extract(failed_swap(&pubkey(30)).error(vec![4, 0, 0, 0]));
+        assert_eq!(failures[0].error.as_ref().unwrap().instruction_index, None);
+        assert!(!failures[0].in_failed_instruction);
+    }
+
+    #[test]
+    fn unrecognized_accounts_are_skipped() {
+        let tx = TransactionBuilder::new(&signature(1), &pubkey(10))
+            .instruction(RAYDIUM, &[pubkey(30)], swap_base_in_data(1_000, 990))
+            .error(slippage_error(0));
+        assert!(extract(tx).is_empty());
+    }
+
+    #[test]
+    fn successful_transactions_are_ignored() {
+        assert!(extract(failed_swap(&pubkey(30))).is_empty());
+    }
+}
@@ -0,0 +1,101 @@
+// graph-node 实体输出：Token / Pool / Trader / Trade，实体定义见 schema.graphql
+
+use crate::pb::io::blockchain::v1::dex::pool::{Pool, PoolSource};
+use crate::pb::io::blockchain::v1::dex::trade::{SwapMode, TradeEvents};
+use substreams::store::{DeltaProto, Deltas};
+use substreams_entity_change::pb::entity::EntityChanges;
+use substreams_entity_change::tables::Tables;
+
+#[substreams::handlers::map]
+fn graph_out(
+    trades: TradeEvents,
+    pools: Deltas<DeltaProto<Pool>>,
+) -> Result<EntityChanges, substreams::errors::Error> {
+    let mut tables = Tables::new();
+
+    // store_pools 为 set_if_not_exists，deltas 只包含首次登记的池子
+    for delta in pools.iter() {
+        let pool = &delta.new_value;
+        for (mint, decimals) in [
+            (&pool.coin_mint, pool.coin_decimals),
+            (&pool.pc_mint, pool.pc_decimals),
+        ] {
+            tables
+                .update_row("Token", mint)
+                .set("decimals", decimals as i32);
+        }
+        tables
+            .create_row("Pool", &pool.address)
+            .set("programAddress", &pool.program_address)
+            .set("coinToken", &pool.coin_mint)
+            .set("pcToken", &pool.pc_mint)
+            .set("coinVault", &pool.coin_vault)
+            .set("pcVault", &pool.pc_vault)
+            .set("lpMint", &pool.lp_mint)
+            .set("marketAddress", &pool.market_address)
+            .set(
+                "source",
+                PoolSource::try_from(pool.source)
+                    .unwrap_or_default()
+                    .as_str_name(),
+            )
+            .set("firstSeenSlot", pool.first_seen_slot);
+    }
+
+    for event in &trades.events {
+        let (Some(trade), Some(block), Some(tx), Some(ix)) = (
+            event.trade.as_ref(),
+            event.block.as_ref(),
+            event.transaction.as_ref(),
+            event.instruction.as_ref(),
+        ) else {
+            continue;
+        };
+        // Trade 的 pool / trader 为必填引用
+        if trade.pool_address.is_empty() || trade.trader().is_empty() {
+            continue;
+        }
+
+        tables
+            .create_row("Trade", &event.event_id)
+            .set("signature", &tx.signature)
+            .set("instructionIndex", ix.index as i32)
+            .set("innerInstructionIndex", ix.inner_instruction_index as i32)
+            .set("slot", block.slot)
+            .set("timestamp", block.timestamp)
+            .set("txIndex", tx.index as i32)
+            .set("pool", &trade.pool_address)
+            .set("trader", trade.trader())
+            .set("tokenSold", trade.sold_mint())
+            .set("tokenBought", trade.bought_mint())
+            .set_bigint_or_zero("amountSold", &trade.user_a_amount)
+            .set_bigint_or_zero("amountBought", &trade.user_b_amount)
+            .set(
+                "swapMode",
+                SwapMode::try_from(trade.swap_mode)
+                    .unwrap_or_default()
+                    .as_str_name(),
+            )
+            .set("priorityFee", tx.priority_fee)
+            .set("jitoTip", tx.jito_tip);
+
+        tables
+            .update_row("Pool", &trade.pool_address)
+            .set("lastTradeSlot", block.slot)
+            .set("lastTradeSignature", &tx.signature);
+        tables
+            .update_row("Trader", trade.trader())
+            .set("lastTradeSlot", block.slot)
+            .set("lastTradeSignature", &tx.signature);
+    }
+
+    let mut changes = tables.to_entity_changes();
+    // Tables 内部为 HashMap，排序以保证输出稳定
+    changes
+        .entity_changes
+        .sort_by(|a, b| (&a.entity, &a.id).cmp(&(&b.entity, &b.id)));
+    for change in &mut changes.entity_changes {
+        change.fields.sort_by(|a, b| a.name.cmp(&b.name));
+    }
+    Ok(changes)
+}
@@ -0,0 +1,57 @@
+// 顶层指令与内层指令统一展开，按执行顺序排列
+
+use substreams_solana::pb::sf::solana::r#type::v1::{Message, TransactionStatusMeta};
+
+pub struct RawInstruction<'a> {
+    pub index: u32,
+    // 内层指令在 meta.inner_instructions[index].instructions 中的位置，顶层为 None
+    pub inner_index: Option<u32>,
+    pub program_id_index: u32,
+    pub accounts: &'a [u8],
+    pub data: &'a [u8],
+}
+
+// 区块内全局序号，用作 store 写入的 ordinal：(交易序号, 顶层指令序号, 内层指令序号)。
+// 顶层指令占低 16 位的 0，内层指令为 inner_index + 1，因此顶层与其第一条内层指令不会重复；
+// 与 flatten 的排序一致，即执行顺序
+pub fn ordinal(tx_index: u32, index: u32, inner_index: Option<u32>) -> u64 {
+    let inner = inner_index.map(|j| j as u64 + 1).unwrap_or(0);
+    ((tx_index as u64) << 32) | ((index as u64) << 16) | inner
+}
+
+// 指令在链上的唯一标识：签名唯一确定交易，(顶层序号, 内层序号) 唯一确定交易内的指令
+pub fn event_id(signature: &str, index: u32, inner_index: Option<u32>) -> String {
+    match inner_index {
+        Some(inner_index) => format!("{}-{}-{}", signature, index, inner_index),
+        None => format!("{}-{}", signature, index),
+    }
+}
+
+pub fn flatten<'a>(
+    message: &'a Message,
+    meta: &'a TransactionStatusMeta,
+) -> Vec<RawInstruction<'a>> {
+    let mut instructions: Vec<RawInstruction> = Vec::new();
+    for (i, ix) in message.instructions.iter().enumerate() {
+        instructions.push(RawInstruction {
+            index: i as u32,
+            inner_index: None,
+            program_id_index: ix.program_id_index,
+            accounts: &ix.accounts,
+            data: &ix.data,
+        });
+    }
+    for inner in &meta.inner_instructions {
+        for (j, ix) in inner.instructions.iter().enumerate() {
+            instructions.push(RawInstruction {
+                index: inner.index,
+                inner_index: Some(j as u32),
+                program_id_index: ix.program_id_index,
+                accounts: &ix.accounts,
+                data: &ix.data,
+            });
+        }
+    }
+    instructions.sort_by_key(|ix| (ix.index, ix.inner_index.map(|j| j + 1).unwrap_or(0)));
+    instructions
+}
@@ -0,0 +1,130 @@
+// Jito 小费识别：同一交易中向 Jito tip 账户的 System Program 转账
+
+use substreams_solana::pb::sf::solana::r#type::v1::ConfirmedTransaction;
+
+const SYSTEM_PROGRAM: &str = "11111111111111111111111111111111";
+const SYSTEM_TRANSFER: u32 = 2;
+
+pub const TIP_ACCOUNTS: [&str; 8] = [
+    "96gYZGLnJYVFmbjzopPSU6QiEV5fGqZNyN9nmNhvrZU5",
+    "HFqU5x63VTqvQss8hp11i4wVV8bD44PvwucfZ2bU7gRe",
+    "Cw8CFyM9FkoMi7K7Crf6HNQqf4uEMzpKw6QNghXLvLkY",
+    "ADaUMid9yfUytqMBgopwjb2DTLSokTSzL1zt6iGPaS49",
+    "DfXygSm4jCyNCybVYYK6DwvWqjKee8pbDmJGcLWNDXjh",
+    "ADuUkR4vqLUMWXxW9gh6D6L8pMSawimctcNZ5pGwDcEt",
+    "DttWaMuVvTiduZRnguLF7jNxTgiMBZ1hyAumKUiL2KRL",
+    "3AVi9Tg9Uo68tJfuvoKvqKNWKkC5wPdSSdeBnizKZ6jT",
+];
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct JitoTip {
+    // 多笔小费累加
+    pub lamports: u64,
+    // 第一笔小费的接收账户
+    pub account: String,
+}
+
+// 遍历顶层与内层指令（部分机器人通过 CPI 支付小费）；未找到返回 None
+pub fn detect_tip(tx: &ConfirmedTransaction) -> Option<JitoTip> {
+    let mut tip: Option<JitoTip> = None;
+
+    for ix in tx.walk_instructions() {
+        if ix.program_id().to_string() != SYSTEM_PROGRAM {
+            continue;
+        }
+        let data = ix.data();
+        let Some(kind) = data.get(..4).and_then(|b| b.try_into().ok()) else {
+            continue;
+        };
+        if u32::from_le_bytes(kind) != SYSTEM_TRANSFER {
+            continue;
+        }
+        let Some(lamports) = data.get(4..12).and_then(|b| b.try_into().ok()) else {
+            continue;
+        };
+        let Some(to) = ix.accounts().get(1).map(|a| a.to_string()) else {
+            continue;
+        };
+        if !TIP_ACCOUNTS.contains(&to.as_str()) {
+            continue;
+        }
+
+        let tip = tip.get_or_insert(JitoTip {
+            lamports: 0,
+            account: to,
+        });
+        tip.lamports += u64::from_le_bytes(lamports);
+    }
+
+    tip
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{pubkey, signature, TransactionBuilder};
+
+    fn transfer(lamports: u64) -> Vec<u8> {
+        let mut data = SYSTEM_TRANSFER.to_le_bytes().to_vec();
+        data.extend_from_slice(&lamports.to_le_bytes());
+        data
+    }
+
+    fn payer() -> String {
+        pubkey(10)
+    }
+
+    fn tx() -> TransactionBuilder {
+        TransactionBuilder::new(&signature(1), &payer())
+    }
+
+    fn to(account: &str) -> Vec<String> {
+        vec![payer(), account.to_string()]
+    }
+
+    #[test]
+    fn transfer_to_each_tip_account_is_detected() {
+        for account in TIP_ACCOUNTS {
+            let tx = tx()
+                .instruction(SYSTEM_PROGRAM, &to(account), transfer(10_000))
+                .build();
+            assert_eq!(
+                detect_tip(&tx),
+                Some(JitoTip {
+                    lamports: 10_000,
+                    account: account.to_string(),
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn multiple_tips_are_summed() {
+        let tx = tx()
+            .instruction(SYSTEM_PROGRAM, &to(TIP_ACCOUNTS[3]), transfer(1_000))
+            .instruction(SYSTEM_PROGRAM, &to(TIP_ACCOUNTS[5]), transfer(2_500))
+            .build();
+        let tip = detect_tip(&tx).unwrap();
+        assert_eq!(tip.lamports, 3_500);
+        assert_eq!(tip.account, TIP_ACCOUNTS[3]);
+    }
+
+    #[test]
+    fn transfer_to_other_account_is_ignored() {
+        let tx = tx()
+            .instruction(SYSTEM_PROGRAM, &to(&pubkey(20)), transfer(10_000))
+            .build();
+        assert_eq!(detect_tip(&tx), None);
+    }
+
+    #[test]
+    fn inner_instruction_tip_is_detected() {
+        // 机器人程序通过 CPI 向 tip 账户转账
+        let bot = pubkey(30);
+        let tx = tx()
+            .instruction(&bot, &to(TIP_ACCOUNTS[0]), vec![])
+            .inner_instruction(0, SYSTEM_PROGRAM, &to(TIP_ACCOUNTS[0]), transfer(42_000))
+            .build();
+        assert_eq!(detect_tip(&tx).map(|tip| tip.lamports), Some(42_000));
+    }
+}
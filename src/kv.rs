@@ -0,0 +1,64 @@
+// substreams-sink-kv 输出：池子最新储备 / 价格与最近一笔成交、钱包最近一笔成交，值为 protobuf 编码
+//
+//  pool:<池子>:reserves    -> PoolReserves（store_pool_reserves 本区块的最终值）
+//  pool:<池子>:last_trade  -> TradeEvent
+//  wallet:<交易者>:last_trade -> TradeEvent
+
+use std::collections::BTreeMap;
+
+use crate::pb::io::blockchain::v1::dex::pool::PoolReserves;
+use crate::pb::io::blockchain::v1::dex::trade::{TradeEvent, TradeEvents};
+use crate::pb::sf::substreams::sink::kv::v1::{kv_operation, KvOperation, KvOperations};
+use prost::Message;
+use substreams::store::{DeltaProto, Deltas};
+
+#[substreams::handlers::map]
+fn kv_out(
+    trades: TradeEvents,
+    reserves: Deltas<DeltaProto<PoolReserves>>,
+) -> Result<KvOperations, substreams::errors::Error> {
+    let mut operations: Vec<KvOperation> = Vec::new();
+
+    // 同一 key 在区块内只保留最后一次写入
+    let mut latest_reserves: BTreeMap<&str, &PoolReserves> = BTreeMap::new();
+    for delta in reserves.iter() {
+        latest_reserves.insert(&delta.key, &delta.new_value);
+    }
+    for (pool, r) in latest_reserves {
+        operations.push(set(
+            format!("pool:{}:reserves", pool),
+            r.encode_to_vec(),
+            r.ordinal,
+        ));
+    }
+
+    let mut last_trades: BTreeMap<String, &TradeEvent> = BTreeMap::new();
+    for event in &trades.events {
+        let Some(trade) = event.trade.as_ref() else {
+            continue;
+        };
+        for key in [
+            format!("pool:{}:last_trade", trade.pool_address),
+            format!("wallet:{}:last_trade", trade.trader()),
+        ] {
+            let slot = last_trades.entry(key).or_insert(event);
+            if event.ordinal > slot.ordinal {
+                *slot = event;
+            }
+        }
+    }
+    for (key, event) in last_trades {
+        operations.push(set(key, event.encode_to_vec(), event.ordinal));
+    }
+
+    Ok(KvOperations { operations })
+}
+
+fn set(key: String, value: Vec<u8>, ordinal: u64) -> KvOperation {
+    KvOperation {
+        key,
+        value,
+        ordinal,
+        r#type: kv_operation::Type::Set as i32,
+    }
+}
@@ -0,0 +1,382 @@
+// 从 meta.log_messages 重建程序调用树
+//
+// 除 Ed25519 / Secp256k1 / Secp256r1 等预编译程序外，每条顶层指令都会产生一条
+// "Program X invoke [1]"，根节点按顺序对应消息中跳过预编译程序后的顶层指令；
+// 子调用按前序遍历的顺序与 meta.inner_instructions[index].instructions 一一对应。
+
+use substreams_solana::pb::sf::solana::r#type::v1::Message;
+
+// 不经过 BPF 执行、不打印调用日志的预编译程序
+const PRECOMPILES: &[&str] = &[
+    "Ed25519SigVerify111111111111111111111111111",
+    "KeccakSecp256k11111111111111111111111111111",
+    "Secp256r1SigVerify1111111111111111111111111",
+];
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Invocation {
+    pub program: String,
+    // invoke [n] 中的 n（顶层为 1）
+    pub depth: u32,
+    // 所属顶层指令序号
+    pub instruction_index: u32,
+    // 在该顶层指令的内层指令列表中的位置，顶层调用为 None
+    pub inner_instruction_index: Option<u32>,
+    // "Program log: " 之后的内容
+    pub logs: Vec<String>,
+    // "Program data: " 之后的内容（base64，Anchor 事件等）
+    pub data: Vec<String>,
+    pub consumed_units: Option<u64>,
+    pub compute_limit: Option<u64>,
+    // success / failed 行，日志截断时为 None
+    pub success: Option<bool>,
+    pub failure: Option<String>,
+    pub children: Vec<Invocation>,
+}
+
+impl Invocation {
+    // 前序遍历自身及全部子调用
+    pub fn walk(&self) -> Vec<&Invocation> {
+        let mut out = vec![self];
+        for child in &self.children {
+            out.extend(child.walk());
+        }
+        out
+    }
+
+    // 本次调用自身打印的 ray_log 内容（不含子调用）
+    pub fn ray_log(&self) -> Option<&str> {
+        self.logs
+            .iter()
+            .find_map(|log| log.strip_prefix("ray_log: "))
+            .map(str::trim)
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct InvocationTree {
+    pub roots: Vec<Invocation>,
+    // 出现 "Log truncated" 时，之后的调用缺失
+    pub truncated: bool,
+}
+
+impl InvocationTree {
+    // programs 为消息中各顶层指令的程序地址（见 top_level_programs），用于跳过预编译程序；
+    // 为空或不足时，其余根节点依次取下一个序号
+    pub fn parse(log_messages: &[String], programs: &[String]) -> InvocationTree {
+        let mut tree = InvocationTree::default();
+        // 当前调用栈（栈顶为正在执行的程序）
+        let mut stack: Vec<Invocation> = Vec::new();
+        let mut next_inner_index: u32 = 0;
+        let mut root_indexes = programs
+            .iter()
+            .enumerate()
+            .filter(|(_, program)| !PRECOMPILES.contains(&program.as_str()))
+            .map(|(index, _)| index as u32);
+        let mut next_root_index: u32 = 0;
+        let mut instruction_index: u32 = 0;
+
+        for line in log_messages {
+            if line == "Log truncated" {
+                tree.truncated = true;
+                break;
+            }
+            let Some(rest) = line.strip_prefix("Program ") else {
+                continue;
+            };
+
+            if let Some(msg) = rest.strip_prefix("log: ") {
+                if let Some(current) = stack.last_mut() {
+                    current.logs.push(msg.to_string());
+                }
+                continue;
+            }
+            if let Some(data) = rest.strip_prefix("data: ") {
+                if let Some(current) = stack.last_mut() {
+                    current.data.push(data.to_string());
+                }
+                continue;
+            }
+
+            let Some((program, event)) = rest.split_once(' ') else {
+                continue;
+            };
+
+            if let Some(depth) = event
+                .strip_prefix("invoke [")
+                .and_then(|d| d.strip_suffix(']'))
+                .and_then(|d| d.parse::<u32>().ok())
+            {
+                let inner_instruction_index = if stack.is_empty() {
+                    next_inner_index = 0;
+                    instruction_index = root_indexes.next().unwrap_or(next_root_index);
+                    next_root_index = instruction_index + 1;
+                    None
+                } else {
+                    next_inner_index += 1;
+                    Some(next_inner_index - 1)
+                };
+                stack.push(Invocation {
+                    program: program.to_string(),
+                    depth,
+                    instruction_index,
+                    inner_instruction_index,
+                    ..Default::default()
+                });
+            } else if let Some(units) = event.strip_prefix("consumed ") {
+                // "consumed N of M compute units"
+                let mut parts = units.split_whitespace();
+                let consumed = parts.next().and_then(|n| n.parse::<u64>().ok());
+                let limit = parts.nth(1).and_then(|n| n.parse::<u64>().ok());
+                if let Some(current) = stack.last_mut() {
+                    if current.program == program {
+                        current.consumed_units = consumed;
+                        current.compute_limit = limit;
+                    }
+                }
+            } else if event == "success" {
+                close(&mut stack, &mut tree.roots, true, None);
+            } else if let Some(reason) = event.strip_prefix("failed: ") {
+                close(&mut stack, &mut tree.roots, false, Some(reason.to_string()));
+            }
+        }
+
+        // 日志截断或缺少结束行时，未闭合的调用照常挂到树上
+        while let Some(node) = stack.pop() {
+            attach(&mut stack, &mut tree.roots, node);
+        }
+
+        tree
+    }
+
+    // 按 (顶层序号, 内层序号) 定位调用，与 CInstruction 的 index / inner_instruction_index 对齐
+    pub fn get(
+        &self,
+        instruction_index: u32,
+        inner_instruction_index: Option<u32>,
+    ) -> Option<&Invocation> {
+        let root = self
+            .roots
+            .iter()
+            .find(|root| root.instruction_index == instruction_index)?;
+        match inner_instruction_index {
+            None => Some(root),
+            Some(j) => root
+                .walk()
+                .into_iter()
+                .find(|inv| inv.inner_instruction_index == Some(j)),
+        }
+    }
+}
+
+// 消息中各顶层指令的程序地址，account_keys 为含 ALT 加载地址的完整账户表
+pub fn top_level_programs(message: &Message, account_keys: &[String]) -> Vec<String> {
+    message
+        .instructions
+        .iter()
+        .map(|ix| {
+            account_keys
+                .get(ix.program_id_index as usize)
+                .cloned()
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+fn close(
+    stack: &mut Vec<Invocation>,
+    roots: &mut Vec<Invocation>,
+    success: bool,
+    failure: Option<String>,
+) {
+    let Some(mut node) = stack.pop() else {
+        return;
+    };
+    node.success = Some(success);
+    node.failure = failure;
+    attach(stack, roots, node);
+}
+
+fn attach(stack: &mut [Invocation], roots: &mut Vec<Invocation>, node: Invocation) {
+    match stack.last_mut() {
+        Some(parent) => parent.children.push(node),
+        None => roots.push(node),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RAYDIUM;
+
+    const JUPITER: &str = "JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4";
+    const TOKEN: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+    const COMPUTE_BUDGET: &str = "ComputeBudget111111111111111111111111111111";
+
+    fn lines(logs: &[&str]) -> Vec<String> {
+        logs.iter().map(|l| l.to_string()).collect()
+    }
+
+    // 聚合器 → Raydium → 两次 Token 转账；顶层指令 0 为 ComputeBudget
+    fn jupiter_route() -> Vec<String> {
+        lines(&[
+            "Program ComputeBudget111111111111111111111111111111 invoke [1]",
+            "Program ComputeBudget111111111111111111111111111111 success",
+            "Program JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4 invoke [1]",
+            "Program log: Instruction: Route",
+            "Program 675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8 invoke [2]",
+            "Program log: ray_log: A0BCDwAAAAAAAAAAAAAAAAACAAAAAAAAAA==",
+            "Program TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA invoke [3]",
+            "Program log: Instruction: Transfer",
+            "Program TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA consumed 4645 of 167311 compute units",
+            "Program TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA success",
+            "Program TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA invoke [3]",
+            "Program log: Instruction: Transfer",
+            "Program TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA consumed 4736 of 159681 compute units",
+            "Program TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA success",
+            "Program 675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8 consumed 31201 of 185922 compute units",
+            "Program 675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8 success",
+            "Program data: QMbN6CYIceIFAAAAAAAAAA==",
+            "Program JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4 consumed 52917 of 199850 compute units",
+            "Program JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4 success",
+        ])
+    }
+
+    #[test]
+    fn nested_cpi_follows_inner_instruction_order() {
+        let tree = InvocationTree::parse(&jupiter_route(), &[]);
+        assert!(!tree.truncated);
+        assert_eq!(tree.roots.len(), 2);
+        assert_eq!(tree.roots[0].program, COMPUTE_BUDGET);
+
+        let route = &tree.roots[1];
+        assert_eq!((route.program.as_str(), route.depth), (JUPITER, 1));
+        assert_eq!(route.instruction_index, 1);
+        assert_eq!(route.inner_instruction_index, None);
+        assert_eq!(route.logs, vec!["Instruction: Route"]);
+        assert_eq!(route.data, vec!["QMbN6CYIceIFAAAAAAAAAA=="]);
+        assert_eq!(route.success, Some(true));
+
+        let raydium = &route.children[0];
+        assert_eq!((raydium.program.as_str(), raydium.depth), (RAYDIUM, 2));
+        assert_eq!(raydium.children.len(), 2);
+        assert_eq!(
+            raydium.ray_log(),
+            Some("A0BCDwAAAAAAAAAAAAAAAAACAAAAAAAAAA==")
+        );
+        // 子调用的日志不计入父调用
+        assert!(route.ray_log().is_none());
+
+        // 前序遍历：Raydium 为内层 0，两次转账为内层 1、2
+        let inner: Vec<(Option<u32>, &str)> = route
+            .walk()
+            .iter()
+            .map(|inv| (inv.inner_instruction_index, inv.program.as_str()))
+            .collect();
+        assert_eq!(
+            inner,
+            vec![
+                (None, JUPITER),
+                (Some(0), RAYDIUM),
+                (Some(1), TOKEN),
+                (Some(2), TOKEN)
+            ]
+        );
+    }
+
+    #[test]
+    fn consumed_units_map_to_inner_instruction_index() {
+        let tree = InvocationTree::parse(&jupiter_route(), &[]);
+        let units = |index: u32, inner: Option<u32>| {
+            tree.get(index, inner)
+                .map(|inv| (inv.consumed_units, inv.compute_limit))
+        };
+        assert_eq!(units(0, None), Some((None, None)));
+        assert_eq!(units(1, None), Some((Some(52_917), Some(199_850))));
+        assert_eq!(units(1, Some(0)), Some((Some(31_201), Some(185_922))));
+        assert_eq!(units(1, Some(1)), Some((Some(4_645), Some(167_311))));
+        assert_eq!(units(1, Some(2)), Some((Some(4_736), Some(159_681))));
+        assert_eq!(units(1, Some(3)), None);
+        assert_eq!(units(2, None), None);
+    }
+
+    #[test]
+    fn failed_inner_program_records_reason() {
+        let tree = InvocationTree::parse(
+            &lines(&[
+            "Program JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4 invoke [1]",
+            "Program log: Instruction: Route",
+            "Program 675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8 invoke [2]",
+            "Program log: Error: exceeds desired slippage limit",
+            "Program 675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8 consumed 17960 of 184331 compute units",
+            "Program 675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8 failed: custom program error: 0x1e",
+            "Program JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4 consumed 33702 of 200000 compute units",
+            "Program JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4 failed: custom program error: 0x1e",
+        ]),
+            &[],
+        );
+        let raydium = tree.get(0, Some(0)).unwrap();
+        assert_eq!(raydium.program, RAYDIUM);
+        assert_eq!(raydium.success, Some(false));
+        assert_eq!(
+            raydium.failure.as_deref(),
+            Some("custom program error: 0x1e")
+        );
+        assert_eq!(raydium.logs, vec!["Error: exceeds desired slippage limit"]);
+        assert_eq!(raydium.consumed_units, Some(17_960));
+        let route = tree.get(0, None).unwrap();
+        assert_eq!(route.success, Some(false));
+        assert_eq!(route.consumed_units, Some(33_702));
+    }
+
+    #[test]
+    fn truncated_logs_keep_open_invocations() {
+        let tree = InvocationTree::parse(
+            &lines(&[
+            "Program ComputeBudget111111111111111111111111111111 invoke [1]",
+            "Program ComputeBudget111111111111111111111111111111 success",
+            "Program JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4 invoke [1]",
+            "Program 675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8 invoke [2]",
+            "Program log: ray_log: A0BCDwAAAAAAAAAAAAAAAAACAAAAAAAAAA==",
+            "Log truncated",
+            "Program 675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8 consumed 31201 of 185922 compute units",
+        ]),
+            &[],
+        );
+        assert!(tree.truncated);
+        assert_eq!(tree.roots.len(), 2);
+        let raydium = tree.get(1, Some(0)).unwrap();
+        assert_eq!(raydium.success, None);
+        // 截断之后的行不再解析
+        assert_eq!(raydium.consumed_units, None);
+        assert!(raydium.ray_log().is_some());
+        assert_eq!(tree.get(1, None).unwrap().success, None);
+    }
+
+    #[test]
+    fn precompile_instruction_is_skipped() {
+        // 顶层指令 0 为 Ed25519 签名校验（无调用日志），指令 1 为 Raydium swap
+        let programs = vec![
+            "Ed25519SigVerify111111111111111111111111111".to_string(),
+            RAYDIUM.to_string(),
+            COMPUTE_BUDGET.to_string(),
+        ];
+        let tree = InvocationTree::parse(
+            &lines(&[
+                "Program 675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8 invoke [1]",
+                "Program log: ray_log: A0BCDwAAAAAAAAAAAAAAAAACAAAAAAAAAA==",
+                "Program 675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8 consumed 31201 of 200000 compute units",
+                "Program 675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8 success",
+                "Program ComputeBudget111111111111111111111111111111 invoke [1]",
+                "Program ComputeBudget111111111111111111111111111111 success",
+            ]),
+            &programs,
+        );
+        assert!(tree.get(0, None).is_none());
+        let raydium = tree.get(1, None).unwrap();
+        assert_eq!(raydium.program, RAYDIUM);
+        assert_eq!(raydium.consumed_units, Some(31_201));
+        assert!(raydium.ray_log().is_some());
+        assert_eq!(tree.get(2, None).unwrap().program, COMPUTE_BUDGET);
+    }
+}
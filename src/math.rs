@@ -0,0 +1,175 @@
+// BigDecimal 的初等函数：substreams 的 BigDecimal 只提供四则运算，ln / exp / sqrt 以级数与牛顿迭代实现，
+// 中间结果保留 WORKING_PRECISION 位有效数字，调用方再按需要的精度截断
+
+use substreams::scalar::{BigDecimal, BigInt};
+
+const WORKING_PRECISION: u64 = 32;
+// 收敛阈值 10^-30
+const EPSILON_EXP: i64 = -30;
+
+// 自然对数：先乘除 2 把 x 缩放到 [0.5, 2]，再用 ln(x) = 2·atanh((x - 1) / (x + 1)) 的级数
+pub fn ln(x: &BigDecimal) -> Option<BigDecimal> {
+    if *x <= BigDecimal::zero() {
+        return None;
+    }
+    let two = BigDecimal::from(2);
+    let half = BigDecimal::try_from("0.5").ok()?;
+    let mut x = x.clone();
+    let mut halvings: i64 = 0;
+    while x > two {
+        x = x / two.clone();
+        halvings += 1;
+    }
+    while x < half {
+        x = x * two.clone();
+        halvings -= 1;
+    }
+    let reduced = atanh_series(&x);
+    if halvings == 0 {
+        return Some(reduced);
+    }
+    Some((reduced + atanh_series(&two) * BigDecimal::from(halvings)).with_prec(WORKING_PRECISION))
+}
+
+// 2·Σ y^(2k+1) / (2k+1)，y = (x - 1) / (x + 1)；x 在 [0.5, 2] 内时 |y| <= 1/3
+fn atanh_series(x: &BigDecimal) -> BigDecimal {
+    let one = BigDecimal::one();
+    let y = ((x.clone() - one.clone()) / (x.clone() + one)).with_prec(WORKING_PRECISION);
+    let y2 = (y.clone() * y.clone()).with_prec(WORKING_PRECISION);
+    let epsilon = epsilon();
+    let mut term = y;
+    let mut sum = BigDecimal::zero();
+    let mut n: u64 = 1;
+    while term.absolute() > epsilon {
+        sum = (sum + term.clone() / BigDecimal::from(n)).with_prec(WORKING_PRECISION);
+        term = (term * y2.clone()).with_prec(WORKING_PRECISION);
+        n += 2;
+    }
+    sum * BigDecimal::from(2)
+}
+
+// 牛顿迭代求平方根，负数返回 None；相对变化小于 10^-30 或迭代 100 次后停止
+pub fn sqrt(x: &BigDecimal) -> Option<BigDecimal> {
+    if *x < BigDecimal::zero() {
+        return None;
+    }
+    if x.is_zero() {
+        return Some(BigDecimal::zero());
+    }
+    let two = BigDecimal::from(2);
+    let epsilon = epsilon();
+    let mut guess = if *x > BigDecimal::one() {
+        x.clone()
+    } else {
+        BigDecimal::one()
+    };
+    for _ in 0..100 {
+        let next = ((guess.clone() + x.clone() / guess.clone()) / two.clone())
+            .with_prec(WORKING_PRECISION);
+        let converged = (next.clone() - guess).absolute() <= epsilon.clone() * next.clone();
+        guess = next;
+        if converged {
+            break;
+        }
+    }
+    Some(guess)
+}
+
+fn epsilon() -> BigDecimal {
+    BigDecimal::new(BigInt::one(), EPSILON_EXP)
+}
+
+// 先除以 2^k 使 |x| <= 0.5，泰勒级数求 exp 后再平方 k 次
+pub fn exp(x: &BigDecimal) -> BigDecimal {
+    let two = BigDecimal::from(2);
+    let half = BigDecimal::try_from("0.5").unwrap_or_default();
+    let mut x = x.clone();
+    let mut squarings = 0;
+    while x.absolute() > half {
+        x = x / two.clone();
+        squarings += 1;
+    }
+    let epsilon = epsilon();
+    let mut term = BigDecimal::one();
+    let mut sum = BigDecimal::one();
+    let mut n: u64 = 1;
+    while term.absolute() > epsilon {
+        term = (term * x.clone() / BigDecimal::from(n)).with_prec(WORKING_PRECISION);
+        sum = (sum + term.clone()).with_prec(WORKING_PRECISION);
+        n += 1;
+    }
+    for _ in 0..squarings {
+        sum = (sum.clone() * sum).with_prec(WORKING_PRECISION);
+    }
+    sum
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decimal(s: &str) -> BigDecimal {
+        BigDecimal::try_from(s).unwrap()
+    }
+
+    fn decimal_close(actual: &BigDecimal, expected: &str) {
+        let diff = (actual.clone() - decimal(expected)).absolute();
+        assert!(
+            diff < decimal("0.000000000000000000001"),
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn natural_log() {
+        decimal_close(&ln(&BigDecimal::one()).unwrap(), "0");
+        decimal_close(
+            &ln(&BigDecimal::from(2)).unwrap(),
+            "0.693147180559945309417232",
+        );
+        decimal_close(
+            &ln(&decimal("0.5")).unwrap(),
+            "-0.693147180559945309417232121",
+        );
+        decimal_close(
+            &ln(&BigDecimal::from(1_000)).unwrap(),
+            "6.907755278982137052053974364",
+        );
+        decimal_close(
+            &ln(&decimal("1.01")).unwrap(),
+            "0.009950330853168082848215357",
+        );
+        assert!(ln(&BigDecimal::zero()).is_none());
+    }
+
+    #[test]
+    fn exponential() {
+        decimal_close(&exp(&BigDecimal::zero()), "1");
+        decimal_close(&exp(&BigDecimal::one()), "2.718281828459045235360287471");
+        decimal_close(&exp(&decimal("-0.5")), "0.606530659712633423603799534");
+        decimal_close(
+            &exp(&BigDecimal::from(10)),
+            "22026.465794806716516957900645",
+        );
+        let x = decimal("-3.25");
+        decimal_close(&ln(&exp(&x)).unwrap(), "-3.25");
+    }
+
+    #[test]
+    fn square_root() {
+        decimal_close(&sqrt(&BigDecimal::from(4)).unwrap(), "2");
+        decimal_close(
+            &sqrt(&BigDecimal::from(2)).unwrap(),
+            "1.414213562373095048801688724",
+        );
+        decimal_close(&sqrt(&decimal("0.0001")).unwrap(), "0.01");
+        decimal_close(
+            &sqrt(&BigDecimal::from(10_000_000_000u64)).unwrap(),
+            "100000",
+        );
+        assert_eq!(sqrt(&BigDecimal::zero()), Some(BigDecimal::zero()));
+        assert!(sqrt(&BigDecimal::from(-1)).is_none());
+    }
+}
@@ -0,0 +1,275 @@
+// Uniswap oracle 式的价格累加器：每个区块把区块开始时的现货价格乘以距上次更新的秒数累加到
+// price_time，并累加区块前后价格的对数收益平方。任意两次观测之差除以时间差即为 TWAP，
+// 平方收益之差开方即为该区间的已实现波动率。
+//
+// 累加器由 store_pool_reserves 的 deltas 驱动（取区块内第一条 old_value 与最后一条 new_value），
+// 观测值按窗口划分成 BUCKETS_PER_WINDOW 个桶，每个桶记录第一次更新时的累加器快照。
+// 窗口之外的桶不会再被读取：store_observation_time 记录最近一个储备有变化的区块时间，
+// store_price_observations 读取其 deltas，删除上次删除之后到本区块之间移出窗口的全部桶
+// （key 以 <窗口>:<桶> 开头，所有池子一次删除），中间隔了很久没有更新也不会遗留。
+
+use std::collections::BTreeMap;
+
+use crate::math::{ln, sqrt};
+use crate::params;
+use crate::pb::io::blockchain::v1::dex::oracle::{PoolTwap, PoolTwaps, PriceObservation};
+use crate::pb::io::blockchain::v1::dex::pool::PoolReserves;
+use substreams::pb::substreams::store_delta::Operation;
+use substreams::scalar::BigDecimal;
+use substreams::store::{
+    DeltaInt64, DeltaProto, Deltas, StoreAdd, StoreAddBigDecimal, StoreDelete, StoreGet,
+    StoreGetBigDecimal, StoreGetProto, StoreNew, StoreSet, StoreSetIfNotExists,
+    StoreSetIfNotExistsProto, StoreSetInt64,
+};
+
+// 未配置 params 时的默认窗口：5m / 1h / 1d
+const DEFAULT_WINDOWS: &[u32] = &[300, 3_600, 86_400];
+const BUCKETS_PER_WINDOW: u32 = 12;
+const PRECISION: u64 = 24;
+
+#[substreams::handlers::store]
+fn store_price_accumulators(reserves: Deltas<DeltaProto<PoolReserves>>, store: StoreAddBigDecimal) {
+    for update in pool_updates(&reserves) {
+        // 新池子没有此前的价格，从下一次更新开始累加
+        let (Some(p0), Some(p1)) = (price(&update.before), price(&update.after)) else {
+            continue;
+        };
+        let elapsed = update.after.timestamp - update.before.timestamp;
+        if elapsed > 0 {
+            store.add(
+                0,
+                accumulator_key(&update.pool, "price_time"),
+                p0.clone() * BigDecimal::from(elapsed),
+            );
+        }
+        if let Some(r) = log_return(&p0, &p1) {
+            store.add(
+                0,
+                accumulator_key(&update.pool, "squared_return"),
+                (r.clone() * r).with_prec(PRECISION),
+            );
+            store.add(
+                0,
+                accumulator_key(&update.pool, "returns"),
+                BigDecimal::one(),
+            );
+        }
+    }
+}
+
+// "time" -> 最近一个储备有变化的区块时间
+#[substreams::handlers::store]
+fn store_observation_time(reserves: Deltas<DeltaProto<PoolReserves>>, store: StoreSetInt64) {
+    // 区块内所有更新共享 block_time
+    if let Some(update) = pool_updates(&reserves).first() {
+        store.set(0, "time", &update.after.timestamp);
+    }
+}
+
+#[substreams::handlers::store]
+fn store_price_observations(
+    params: String,
+    reserves: Deltas<DeltaProto<PoolReserves>>,
+    times: Deltas<DeltaInt64>,
+    accumulators: StoreGetBigDecimal,
+    store: StoreSetIfNotExistsProto<PriceObservation>,
+) {
+    // store 无法返回错误，params 非法时与 map_pool_twaps 一样使模块失败
+    let windows = parse_windows(&params).unwrap_or_else(|e| panic!("{}", e));
+
+    for delta in times.iter().filter(|d| d.operation == Operation::Update) {
+        for &window in &windows {
+            for bucket in expired_buckets(window, delta.old_value, delta.new_value) {
+                store.delete_prefix(0, &bucket_prefix(window, bucket));
+            }
+        }
+    }
+
+    let updates = pool_updates(&reserves);
+    for update in updates {
+        let now = update.after.timestamp;
+        let observation = observe(&accumulators, &update.pool, now);
+        for &window in &windows {
+            let bucket = now.div_euclid(bucket_size(window));
+            store.set_if_not_exists(
+                0,
+                observation_key(window, &update.pool, bucket),
+                &observation,
+            );
+        }
+    }
+}
+
+#[substreams::handlers::map]
+fn map_pool_twaps(
+    params: String,
+    reserves: Deltas<DeltaProto<PoolReserves>>,
+    accumulators: StoreGetBigDecimal,
+    observations: StoreGetProto<PriceObservation>,
+) -> Result<PoolTwaps, substreams::errors::Error> {
+    let windows = parse_windows(&params)?;
+    let mut twaps: Vec<PoolTwap> = Vec::new();
+
+    for update in pool_updates(&reserves) {
+        let now = update.after.timestamp;
+        let current = observe(&accumulators, &update.pool, now);
+
+        for &window in &windows {
+            // 窗口内最早的观测：从 now - window 所在的桶往后找
+            let size = bucket_size(window);
+            let since = now - window as i64;
+            let Some(start) = (since.div_euclid(size)..=now.div_euclid(size))
+                .filter_map(|bucket| {
+                    observations.get_last(observation_key(window, &update.pool, bucket))
+                })
+                .find(|o| o.timestamp >= since && o.timestamp < now)
+            else {
+                continue;
+            };
+
+            let elapsed = BigDecimal::from(now - start.timestamp);
+            let price_time =
+                decimal(&current.price_time_cumulative) - decimal(&start.price_time_cumulative);
+            let squared = decimal(&current.squared_return_cumulative)
+                - decimal(&start.squared_return_cumulative);
+            let volatility = sqrt(&squared).unwrap_or_default().with_prec(PRECISION);
+
+            twaps.push(PoolTwap {
+                pool_address: update.pool.clone(),
+                window,
+                start_time: start.timestamp,
+                end_time: now,
+                twap: (price_time / elapsed).with_prec(PRECISION).to_string(),
+                volatility: volatility.to_string(),
+                return_count: current.return_count.saturating_sub(start.return_count),
+                spot_price: update.after.price.clone(),
+            });
+        }
+    }
+
+    Ok(PoolTwaps { twaps })
+}
+
+// 区块内某个池子储备的变化：区块开始前与区块结束后
+struct PoolUpdate {
+    pool: String,
+    before: PoolReserves,
+    after: PoolReserves,
+}
+
+fn pool_updates(deltas: &Deltas<DeltaProto<PoolReserves>>) -> Vec<PoolUpdate> {
+    let mut updates: BTreeMap<&str, PoolUpdate> = BTreeMap::new();
+    for delta in deltas.iter() {
+        updates
+            .entry(delta.key.as_str())
+            .and_modify(|u| u.after = delta.new_value.clone())
+            .or_insert_with(|| PoolUpdate {
+                pool: delta.key.clone(),
+                before: delta.old_value.clone(),
+                after: delta.new_value.clone(),
+            });
+    }
+    updates.into_values().collect()
+}
+
+fn observe(accumulators: &StoreGetBigDecimal, pool: &str, timestamp: i64) -> PriceObservation {
+    let get = |name: &str| {
+        accumulators
+            .get_last(accumulator_key(pool, name))
+            .unwrap_or_default()
+    };
+    PriceObservation {
+        timestamp,
+        price_time_cumulative: get("price_time").to_string(),
+        squared_return_cumulative: get("squared_return").to_string(),
+        return_count: get("returns").to_string().parse().unwrap_or_default(),
+    }
+}
+
+fn price(reserves: &PoolReserves) -> Option<BigDecimal> {
+    let p = BigDecimal::try_from(reserves.price.as_str()).ok()?;
+    (p > BigDecimal::zero()).then_some(p)
+}
+
+fn log_return(p0: &BigDecimal, p1: &BigDecimal) -> Option<BigDecimal> {
+    ln(&(p1.clone() / p0.clone()).with_prec(PRECISION + 8)).map(|r| r.with_prec(PRECISION))
+}
+
+// store_price_observations 与 map_pool_twaps 共用，保证两者的窗口一致
+fn parse_windows(params: &str) -> Result<Vec<u32>, substreams::errors::Error> {
+    params::parse_durations(params, DEFAULT_WINDOWS)
+}
+
+// 上一次更新（previous）时已删除 expired(previous) 及之前的桶，此时存在的桶不晚于 previous 所在的桶；
+// 本区块（now）需要删除的是两者之间、不晚于 expired(now) 的桶
+fn expired_buckets(window: u32, previous: i64, now: i64) -> std::ops::RangeInclusive<i64> {
+    let size = bucket_size(window);
+    let expired = |t: i64| (t - window as i64).div_euclid(size) - 1;
+    let last = expired(now).min(previous.div_euclid(size));
+    expired(previous) + 1..=last
+}
+
+fn decimal(s: &str) -> BigDecimal {
+    BigDecimal::try_from(s).unwrap_or_default()
+}
+
+fn bucket_size(window: u32) -> i64 {
+    (window / BUCKETS_PER_WINDOW).max(1) as i64
+}
+
+fn accumulator_key(pool: &str, name: &str) -> String {
+    format!("{}:{}", pool, name)
+}
+
+fn observation_key(window: u32, pool: &str, bucket: i64) -> String {
+    format!("{}{}", bucket_prefix(window, bucket), pool)
+}
+
+fn bucket_prefix(window: u32, bucket: i64) -> String {
+    format!("{}:{}:", window, bucket)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decimal_close(actual: &BigDecimal, expected: &str) {
+        let diff = (actual.clone() - decimal(expected)).absolute();
+        assert!(
+            diff < decimal("0.000000000000000000001"),
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn log_return_is_symmetric() {
+        let up = log_return(&BigDecimal::from(100), &BigDecimal::from(110)).unwrap();
+        let down = log_return(&BigDecimal::from(110), &BigDecimal::from(100)).unwrap();
+        decimal_close(&up, "0.095310179804324860043952123");
+        decimal_close(&(up + down), "0");
+    }
+
+    #[test]
+    fn consecutive_blocks_expire_one_bucket() {
+        // 窗口 300 秒，桶大小 25 秒：t=1000 时最早读取 700 所在的桶 28，已删除到 27
+        assert_eq!(expired_buckets(300, 1_000, 1_025), 28..=28);
+        // 同一个桶内的下一个区块没有新移出窗口的桶
+        assert!(expired_buckets(300, 1_000, 1_010).is_empty());
+    }
+
+    #[test]
+    fn gap_expires_every_bucket_since_last_prune() {
+        assert_eq!(expired_buckets(300, 1_000, 1_100), 28..=31);
+        // 很久之后再更新：只需删除到 previous 所在的桶为止
+        assert_eq!(expired_buckets(300, 1_000, 100_000), 28..=40);
+    }
+
+    #[test]
+    fn windows_are_validated_by_shared_parse() {
+        assert_eq!(parse_windows("").unwrap(), DEFAULT_WINDOWS.to_vec());
+        assert_eq!(parse_windows("15m,4h").unwrap(), vec![900, 14_400]);
+        assert!(parse_windows("15x").is_err());
+    }
+}
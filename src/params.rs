@@ -0,0 +1,66 @@
+// 模块 params 解析
+
+// 逗号分隔的时长，如 "1m,5m,1h,1d"；单位 s / m / h / d，纯数字按秒计。为空时返回 default
+pub fn parse_durations(
+    params: &str,
+    default: &[u32],
+) -> Result<Vec<u32>, substreams::errors::Error> {
+    let params = params.trim();
+    if params.is_empty() {
+        return Ok(default.to_vec());
+    }
+
+    let mut intervals = Vec::new();
+    for item in params.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let (number, unit) = match item.char_indices().last() {
+            Some((i, c)) if c.is_ascii_alphabetic() => (&item[..i], c),
+            _ => (item, 's'),
+        };
+        let multiplier = match unit {
+            's' => 1,
+            'm' => 60,
+            'h' => 3_600,
+            'd' => 86_400,
+            _ => return Err(invalid_duration(item)),
+        };
+        let seconds = number
+            .parse::<u32>()
+            .ok()
+            .and_then(|n| n.checked_mul(multiplier))
+            .filter(|&s| s > 0)
+            .ok_or_else(|| invalid_duration(item))?;
+        intervals.push(seconds);
+    }
+    intervals.sort_unstable();
+    intervals.dedup();
+    Ok(intervals)
+}
+
+fn invalid_duration(item: &str) -> substreams::errors::Error {
+    substreams::errors::Error::msg(format!("invalid duration: {}", item))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn units_are_converted_sorted_and_deduplicated() {
+        assert_eq!(
+            parse_durations(" 1h, 90, 5m,1d,60s ,1m,", &[]).unwrap(),
+            vec![60, 90, 300, 3_600, 86_400]
+        );
+    }
+
+    #[test]
+    fn empty_params_use_default() {
+        assert_eq!(parse_durations("  ", &[60, 300]).unwrap(), vec![60, 300]);
+    }
+
+    #[test]
+    fn invalid_durations_are_rejected() {
+        for params in ["0m", "5w", "m", "-1h", "1.5h", "50000d"] {
+            assert!(parse_durations(params, &[60]).is_err(), "{}", params);
+        }
+    }
+}
@@ -0,0 +1,452 @@
+// 交易者持仓与已实现盈亏：以 (交易者, coin mint, pc mint) 为单位，持仓为 coin，成本与盈亏以 pc 计
+//
+// 成本按移动加权平均：买入把所付 pc 计入持仓成本，卖出按平均成本减少持仓成本（平均成本不变），
+// 持仓归零（或转负）时成本清零。store 无法读取自身状态，而卖出对成本是乘法（乘以剩余比例
+// after / before），因此拆成只做累加 / 重置的 set_sum store：
+// store_trader_positions 累加净持仓与买卖两侧的数量 / 金额；
+// store_trader_sell_factor 累加每笔卖出的 ln(after / before)，即 ln F，F 为建仓以来卖出后剩余比例之积；
+// store_trader_cost_basis 累加买入成本 / F（按 F 归一化，之后的卖出不再改变它）；
+// 任意时刻的持仓成本为 归一化成本 × F，平均成本为 持仓成本 / 持仓。
+// store_trader_realized_pnl 在每笔卖出时按交易前的平均成本累加 卖出所得 - 卖出数量 × 平均成本。
+
+use crate::math::{exp, ln};
+use crate::pb::io::blockchain::v1::dex::pnl::{TraderPnl, TraderPnls};
+use crate::pb::io::blockchain::v1::dex::pool::Pool;
+use crate::pb::io::blockchain::v1::dex::trade::{TradeEvent, TradeEvents};
+use crate::price::{self, TradePrice};
+use substreams::scalar::BigDecimal;
+use substreams::store::{
+    StoreAdd, StoreAddBigDecimal, StoreGet, StoreGetBigDecimal, StoreGetProto, StoreNew,
+    StoreSetSum, StoreSetSumBigDecimal,
+};
+
+const PRECISION: u64 = 24;
+
+#[substreams::handlers::store]
+fn store_trader_positions(
+    trades: TradeEvents,
+    pools: StoreGetProto<Pool>,
+    store: StoreAddBigDecimal,
+) {
+    for t in position_trades(&trades, &pools) {
+        let key = position_key(&t);
+        let ordinal = t.event.ordinal;
+        let amount = t.price.base_volume.clone();
+        let quote = t.price.quote_volume.clone();
+        if t.is_buy {
+            store.add(ordinal, format!("{}:position", key), &amount);
+            store.add(ordinal, format!("{}:bought", key), &amount);
+            store.add(ordinal, format!("{}:spent", key), &quote);
+        } else {
+            store.add(ordinal, format!("{}:position", key), amount.neg());
+            store.add(ordinal, format!("{}:sold", key), &amount);
+            store.add(ordinal, format!("{}:proceeds", key), &quote);
+        }
+    }
+}
+
+#[substreams::handlers::store]
+fn store_trader_sell_factor(
+    trades: TradeEvents,
+    pools: StoreGetProto<Pool>,
+    positions: StoreGetBigDecimal,
+    store: StoreSetSumBigDecimal,
+) {
+    for t in position_trades(&trades, &pools) {
+        let (before, after) = position_change(&t, &positions);
+        if let Some(update) = sell_factor_update(&t, &before, &after) {
+            update.apply(&store, t.event.ordinal, position_key(&t));
+        }
+    }
+}
+
+#[substreams::handlers::store]
+fn store_trader_cost_basis(
+    trades: TradeEvents,
+    pools: StoreGetProto<Pool>,
+    positions: StoreGetBigDecimal,
+    factors: StoreGetBigDecimal,
+    store: StoreSetSumBigDecimal,
+) {
+    for t in position_trades(&trades, &pools) {
+        let key = position_key(&t);
+        let (before, after) = position_change(&t, &positions);
+        // 买入不改变 F，取交易前的值即可
+        let log_factor = value_before(&factors, &key, t.event.ordinal);
+        if let Some(update) = cost_basis_update(&t, &before, &after, &log_factor) {
+            update.apply(&store, t.event.ordinal, key);
+        }
+    }
+}
+
+#[substreams::handlers::store]
+fn store_trader_realized_pnl(
+    trades: TradeEvents,
+    pools: StoreGetProto<Pool>,
+    positions: StoreGetBigDecimal,
+    factors: StoreGetBigDecimal,
+    costs: StoreGetBigDecimal,
+    store: StoreAddBigDecimal,
+) {
+    let basis = CostBasis {
+        positions,
+        factors,
+        costs,
+    };
+    for t in position_trades(&trades, &pools) {
+        if let Some(pnl) = basis.realized_pnl(&t) {
+            store.add(t.event.ordinal, position_key(&t), pnl);
+        }
+    }
+}
+
+#[substreams::handlers::map]
+fn map_trader_pnl(
+    trades: TradeEvents,
+    pools: StoreGetProto<Pool>,
+    positions: StoreGetBigDecimal,
+    factors: StoreGetBigDecimal,
+    costs: StoreGetBigDecimal,
+    realized: StoreGetBigDecimal,
+) -> Result<TraderPnls, substreams::errors::Error> {
+    let basis = CostBasis {
+        positions,
+        factors,
+        costs,
+    };
+    let mut pnls: Vec<TraderPnl> = Vec::new();
+
+    for t in position_trades(&trades, &pools) {
+        let key = position_key(&t);
+        let ordinal = t.event.ordinal;
+        let block = t.event.block.clone().unwrap_or_default();
+        let (_, position) = position_change(&t, &basis.positions);
+
+        let realized_pnl = basis.realized_pnl(&t);
+        pnls.push(TraderPnl {
+            trader_address: t.trader.to_string(),
+            mint: t.pool.coin_mint.clone(),
+            quote_mint: t.pool.pc_mint.clone(),
+            pool_address: t.pool.address.clone(),
+            slot: block.slot,
+            timestamp: block.timestamp,
+            signature: t
+                .event
+                .transaction
+                .as_ref()
+                .map(|tx| tx.signature.clone())
+                .unwrap_or_default(),
+            ordinal,
+            is_buy: t.is_buy,
+            amount: t.price.base_volume.to_string(),
+            quote_amount: t.price.quote_volume.to_string(),
+            average_cost: basis
+                .average_cost_after(&key, ordinal, &position)
+                .map(|c| c.to_string())
+                .unwrap_or_default(),
+            position: position.to_string(),
+            cost_basis_known: t.is_buy || realized_pnl.is_some(),
+            realized_pnl: realized_pnl.unwrap_or_default().to_string(),
+            realized_pnl_total: realized
+                .get_at(ordinal, key)
+                .unwrap_or_default()
+                .to_string(),
+        });
+    }
+
+    Ok(TraderPnls { pnls })
+}
+
+struct PositionTrade<'a> {
+    event: &'a TradeEvent,
+    pool: Pool,
+    trader: &'a str,
+    price: TradePrice,
+    is_buy: bool,
+}
+
+// 可计入持仓的交易：池子已登记且两侧数量可解析
+fn position_trades<'a>(
+    trades: &'a TradeEvents,
+    pools: &StoreGetProto<Pool>,
+) -> Vec<PositionTrade<'a>> {
+    trades
+        .events
+        .iter()
+        .filter_map(|event| {
+            let trade = event.trade.as_ref()?;
+            let pool = pools.get_last(&trade.pool_address)?;
+            let price = price::trade_price(trade, &pool)?;
+            Some(PositionTrade {
+                event,
+                is_buy: trade.bought_mint() == pool.coin_mint,
+                trader: trade.trader(),
+                pool,
+                price,
+            })
+        })
+        .collect()
+}
+
+// set_sum store 的一次写入
+#[derive(Debug, PartialEq)]
+enum Update {
+    Set(BigDecimal),
+    Sum(BigDecimal),
+}
+
+impl Update {
+    fn apply(self, store: &StoreSetSumBigDecimal, ordinal: u64, key: String) {
+        match self {
+            Update::Set(value) => store.set(ordinal, key, value),
+            Update::Sum(value) => store.sum(ordinal, key, value),
+        }
+    }
+}
+
+// 本笔交易前后的持仓
+fn position_change(t: &PositionTrade, positions: &StoreGetBigDecimal) -> (BigDecimal, BigDecimal) {
+    let after = positions
+        .get_at(t.event.ordinal, format!("{}:position", position_key(t)))
+        .unwrap_or_default();
+    let before = if t.is_buy {
+        after.clone() - t.price.base_volume.clone()
+    } else {
+        after.clone() + t.price.base_volume.clone()
+    };
+    (before, after)
+}
+
+// 卖出后剩余比例的对数；清仓时重置
+fn sell_factor_update(
+    t: &PositionTrade,
+    before: &BigDecimal,
+    after: &BigDecimal,
+) -> Option<Update> {
+    if t.is_buy {
+        return None;
+    }
+    if *after <= BigDecimal::zero() {
+        return Some(Update::Set(BigDecimal::zero()));
+    }
+    if *before <= BigDecimal::zero() {
+        return None;
+    }
+    ln(&(after.clone() / before.clone()).with_prec(PRECISION + 8))
+        .map(|r| Update::Sum(r.with_prec(PRECISION)))
+}
+
+// 买入成本按交易前的 F 归一化后累加；清仓时重置。从负持仓买回时，只有转为正持仓的部分计入成本
+fn cost_basis_update(
+    t: &PositionTrade,
+    before: &BigDecimal,
+    after: &BigDecimal,
+    log_factor: &BigDecimal,
+) -> Option<Update> {
+    let zero = BigDecimal::zero();
+    if *after <= zero {
+        return (!t.is_buy).then_some(Update::Set(zero));
+    }
+    if !t.is_buy {
+        return None;
+    }
+    let quote = t.price.quote_volume.clone();
+    if *before < zero {
+        let opened = quote * after.clone() / t.price.base_volume.clone();
+        return Some(Update::Set(opened.with_prec(PRECISION)));
+    }
+    Some(Update::Sum(
+        (quote * exp(&log_factor.neg())).with_prec(PRECISION),
+    ))
+}
+
+// 持仓成本 = 归一化成本 × F；持仓为正且成本为正时才有平均成本
+fn average_cost(
+    position: &BigDecimal,
+    normalized_cost: &BigDecimal,
+    log_factor: &BigDecimal,
+) -> Option<BigDecimal> {
+    let zero = BigDecimal::zero();
+    if *position <= zero || *normalized_cost <= zero {
+        return None;
+    }
+    let cost = normalized_cost.clone() * exp(log_factor);
+    Some((cost / position.clone()).with_prec(PRECISION))
+}
+
+// 卖出的已实现盈亏；买入为 0，交易前没有成本记录时无法计算
+fn realized_pnl(t: &PositionTrade, average_cost_before: Option<BigDecimal>) -> Option<BigDecimal> {
+    if t.is_buy {
+        return Some(BigDecimal::zero());
+    }
+    let cost = average_cost_before?;
+    Some((t.price.quote_volume.clone() - t.price.base_volume.clone() * cost).with_prec(PRECISION))
+}
+
+struct CostBasis {
+    positions: StoreGetBigDecimal,
+    factors: StoreGetBigDecimal,
+    costs: StoreGetBigDecimal,
+}
+
+impl CostBasis {
+    fn realized_pnl(&self, t: &PositionTrade) -> Option<BigDecimal> {
+        let key = position_key(t);
+        let ordinal = t.event.ordinal;
+        let (before, _) = position_change(t, &self.positions);
+        let average = average_cost(
+            &before,
+            &value_before(&self.costs, &key, ordinal),
+            &value_before(&self.factors, &key, ordinal),
+        );
+        realized_pnl(t, average)
+    }
+
+    fn average_cost_after(
+        &self,
+        key: &str,
+        ordinal: u64,
+        position: &BigDecimal,
+    ) -> Option<BigDecimal> {
+        let at = |store: &StoreGetBigDecimal| store.get_at(ordinal, key).unwrap_or_default();
+        average_cost(position, &at(&self.costs), &at(&self.factors))
+    }
+}
+
+// 同一笔交易可能更新了该 key，交易前的值取上一个 ordinal；ordinal 为 0 时取区块开始时的值
+fn value_before(store: &StoreGetBigDecimal, key: &str, ordinal: u64) -> BigDecimal {
+    match ordinal.checked_sub(1) {
+        Some(previous) => store.get_at(previous, key),
+        None => store.get_first(key),
+    }
+    .unwrap_or_default()
+}
+
+fn position_key(t: &PositionTrade) -> String {
+    format!("{}:{}:{}", t.trader, t.pool.coin_mint, t.pool.pc_mint)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 按 store 的顺序在内存中模拟一个持仓：返回每笔交易的已实现盈亏与交易后的平均成本
+    fn simulate(trades: &[(bool, i64, i64)]) -> Vec<(Option<BigDecimal>, Option<BigDecimal>)> {
+        let event = TradeEvent::default();
+        let mut position = BigDecimal::zero();
+        let mut log_factor = BigDecimal::zero();
+        let mut normalized_cost = BigDecimal::zero();
+        let apply = |value: &mut BigDecimal, update: Option<Update>| match update {
+            Some(Update::Set(v)) => *value = v,
+            Some(Update::Sum(v)) => *value = value.clone() + v,
+            None => {}
+        };
+
+        let mut results = Vec::new();
+        for &(is_buy, amount, quote) in trades {
+            let t = PositionTrade {
+                event: &event,
+                pool: Pool::default(),
+                trader: "trader",
+                price: TradePrice {
+                    price: BigDecimal::zero(),
+                    base_volume: BigDecimal::from(amount),
+                    quote_volume: BigDecimal::from(quote),
+                },
+                is_buy,
+            };
+            let before = position.clone();
+            let after = if is_buy {
+                before.clone() + amount
+            } else {
+                before.clone() - amount
+            };
+            let realized = realized_pnl(&t, average_cost(&before, &normalized_cost, &log_factor));
+            let cost_update = cost_basis_update(&t, &before, &after, &log_factor);
+            apply(&mut log_factor, sell_factor_update(&t, &before, &after));
+            apply(&mut normalized_cost, cost_update);
+            position = after;
+            results.push((
+                realized,
+                average_cost(&position, &normalized_cost, &log_factor),
+            ));
+        }
+        results
+    }
+
+    fn assert_close(actual: &Option<BigDecimal>, expected: Option<i64>) {
+        match (actual, expected) {
+            (Some(a), Some(e)) => {
+                let diff = (a.clone() - BigDecimal::from(e)).absolute();
+                assert!(
+                    diff < BigDecimal::try_from("0.000000000000000001").unwrap(),
+                    "{} != {}",
+                    a,
+                    e
+                );
+            }
+            (None, None) => {}
+            _ => panic!(
+                "{:?} != {:?}",
+                actual.as_ref().map(|a| a.to_string()),
+                expected
+            ),
+        }
+    }
+
+    fn check(trades: &[(bool, i64, i64)], expected: &[(Option<i64>, Option<i64>)]) {
+        let results = simulate(trades);
+        assert_eq!(results.len(), expected.len());
+        for ((realized, average), (expected_realized, expected_average)) in
+            results.iter().zip(expected)
+        {
+            assert_close(realized, *expected_realized);
+            assert_close(average, *expected_average);
+        }
+    }
+
+    #[test]
+    fn round_trips_reset_cost_basis() {
+        // 买 1 @10，卖 1 @20，再买 1 @30，卖 1 @30：第二次卖出盈亏为 0
+        check(
+            &[(true, 1, 10), (false, 1, 20), (true, 1, 30), (false, 1, 30)],
+            &[
+                (Some(0), Some(10)),
+                (Some(10), None),
+                (Some(0), Some(30)),
+                (Some(0), None),
+            ],
+        );
+    }
+
+    #[test]
+    fn partial_sells_keep_average_cost() {
+        check(
+            &[(true, 2, 20), (false, 1, 15), (false, 1, 30)],
+            &[(Some(0), Some(10)), (Some(5), Some(10)), (Some(20), None)],
+        );
+    }
+
+    #[test]
+    fn buy_after_partial_sell_averages_remaining_cost() {
+        // 剩余 1 个成本 10，再以 40 买入 1 个：平均成本 25
+        check(
+            &[(true, 2, 20), (false, 1, 20), (true, 1, 40), (false, 2, 60)],
+            &[
+                (Some(0), Some(10)),
+                (Some(10), Some(10)),
+                (Some(0), Some(25)),
+                (Some(10), None),
+            ],
+        );
+    }
+
+    #[test]
+    fn selling_without_recorded_buy_has_unknown_cost() {
+        // 卖出起始区块之前的持仓，之后买回：只有转为正持仓的 1 个计入成本
+        check(
+            &[(false, 2, 30), (true, 3, 30), (false, 1, 25)],
+            &[(None, None), (Some(0), Some(10)), (Some(15), None)],
+        );
+    }
+}
@@ -0,0 +1,214 @@
+// 池子注册表：从 Initialize2 与 swap 账户布局中学习池子的 mint、金库、LP mint 与市场
+//
+// store_pools 为 set_if_not_exists，第一次写入即固定，因此只登记 mint 与 decimals 都已知的池子：
+// 金库在交易中没有代币余额条目时跳过，等之后带有余额的交易再登记。
+
+use crate::instructions;
+use crate::pb::io::blockchain::v1::dex::pool::{Pool, PoolSource, Pools};
+use crate::raydium::{self, InitializeAccounts, SwapAccounts};
+use crate::RAYDIUM;
+use std::collections::HashSet;
+use substreams::store::{StoreNew, StoreSetIfNotExists, StoreSetIfNotExistsProto};
+use substreams_solana::pb::sf::solana::r#type::v1::{Block, TransactionStatusMeta};
+
+#[substreams::handlers::map]
+fn map_pools(block: Block) -> Result<Pools, substreams::errors::Error> {
+    Ok(Pools {
+        pools: extract_pools(&block),
+    })
+}
+
+// 区块内新见到的池子（同一区块内按地址去重，保留第一次出现）
+pub fn extract_pools(block: &Block) -> Vec<Pool> {
+    let mut pools: Vec<Pool> = Vec::new();
+    let mut seen: HashSet<String> = HashSet::new();
+
+    for tx in block.transactions() {
+        let Some(meta) = tx.meta.as_ref() else {
+            continue;
+        };
+        let Some(message) = tx.transaction.as_ref().and_then(|t| t.message.as_ref()) else {
+            continue;
+        };
+        let account_keys: Vec<String> = tx
+            .resolved_accounts()
+            .iter()
+            .map(|k| bs58::encode(k).into_string())
+            .collect();
+
+        for ix in instructions::flatten(message, meta) {
+            if account_keys
+                .get(ix.program_id_index as usize)
+                .map(String::as_str)
+                != Some(RAYDIUM)
+            {
+                continue;
+            }
+            let accounts: Vec<String> = ix
+                .accounts
+                .iter()
+                .filter_map(|&acc_idx| account_keys.get(acc_idx as usize).cloned())
+                .collect();
+
+            let pool = match ix.data.first() {
+                Some(&raydium::INITIALIZE2) => InitializeAccounts::from_accounts(&accounts)
+                    .and_then(|init| {
+                        let (_, coin_decimals) =
+                            vault_token(meta, &account_keys, &init.pool_coin_token_account)?;
+                        let (_, pc_decimals) =
+                            vault_token(meta, &account_keys, &init.pool_pc_token_account)?;
+                        Some(Pool {
+                            address: init.amm,
+                            program_address: RAYDIUM.to_string(),
+                            coin_decimals,
+                            pc_decimals,
+                            coin_mint: init.coin_mint,
+                            pc_mint: init.pc_mint,
+                            coin_vault: init.pool_coin_token_account,
+                            pc_vault: init.pool_pc_token_account,
+                            lp_mint: init.lp_mint,
+                            market_address: init.market,
+                            first_seen_slot: block.slot,
+                            source: PoolSource::Initialize as i32,
+                        })
+                    }),
+                Some(&raydium::SWAP_BASE_IN) | Some(&raydium::SWAP_BASE_OUT) => {
+                    // swap 布局不含 mint，从金库账户的代币余额中取
+                    SwapAccounts::from_accounts(&accounts).and_then(|swap| {
+                        let (coin_mint, coin_decimals) =
+                            vault_token(meta, &account_keys, &swap.pool_coin_token_account)?;
+                        let (pc_mint, pc_decimals) =
+                            vault_token(meta, &account_keys, &swap.pool_pc_token_account)?;
+                        Some(Pool {
+                            program_address: RAYDIUM.to_string(),
+                            coin_mint,
+                            pc_mint,
+                            coin_decimals,
+                            pc_decimals,
+                            address: swap.amm,
+                            coin_vault: swap.pool_coin_token_account,
+                            pc_vault: swap.pool_pc_token_account,
+                            lp_mint: String::new(),
+                            market_address: swap.serum_market,
+                            first_seen_slot: block.slot,
+                            source: PoolSource::Swap as i32,
+                        })
+                    })
+                }
+                _ => None,
+            };
+
+            if let Some(pool) = pool {
+                if seen.insert(pool.address.clone()) {
+                    pools.push(pool);
+                }
+            }
+        }
+    }
+
+    pools
+}
+
+// 以池子地址为 key，只记录第一次见到的信息（first_seen_slot 不会被后续区块覆盖）
+#[substreams::handlers::store]
+fn store_pools(pools: Pools, store: StoreSetIfNotExistsProto<Pool>) {
+    for pool in pools.pools {
+        store.set_if_not_exists(0, &pool.address, &pool);
+    }
+}
+
+fn token_balance<'a>(
+    meta: &'a TransactionStatusMeta,
+    account_keys: &[String],
+    address: &str,
+) -> Option<&'a substreams_solana::pb::sf::solana::r#type::v1::TokenBalance> {
+    let idx = account_keys.iter().position(|k| k == address)? as u32;
+    meta.pre_token_balances
+        .iter()
+        .chain(meta.post_token_balances.iter())
+        .find(|b| b.account_index == idx)
+}
+
+// 金库的 (mint, decimals)；交易中没有该金库的代币余额条目时为 None
+fn vault_token(
+    meta: &TransactionStatusMeta,
+    account_keys: &[String],
+    vault: &str,
+) -> Option<(String, u32)> {
+    let balance = token_balance(meta, account_keys, vault)?;
+    if balance.mint.is_empty() {
+        return None;
+    }
+    Some((
+        balance.mint.clone(),
+        balance.ui_token_amount.as_ref()?.decimals,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{
+        pubkey, signature, swap_base_in_data, BlockBuilder, SwapAccountsSpec, TransactionBuilder,
+    };
+
+    fn swap(signature_seed: u8) -> TransactionBuilder {
+        let accounts = SwapAccountsSpec {
+            amm: &pubkey(30),
+            amm_authority: &pubkey(13),
+            pool_coin_token_account: &pubkey(31),
+            pool_pc_token_account: &pubkey(32),
+            user_source: &pubkey(16),
+            user_destination: &pubkey(17),
+            user_owner: &pubkey(10),
+        }
+        .to_accounts();
+        TransactionBuilder::new(&signature(signature_seed), &pubkey(10)).instruction(
+            RAYDIUM,
+            &accounts,
+            swap_base_in_data(1_000, 0),
+        )
+    }
+
+    fn with_vaults(tx: TransactionBuilder) -> TransactionBuilder {
+        tx.token_balance(&pubkey(31), &pubkey(18), &pubkey(13), 9, Some(1), Some(2))
+            .token_balance(&pubkey(32), &pubkey(19), &pubkey(13), 6, Some(2), Some(1))
+    }
+
+    #[test]
+    fn swap_registers_pool_from_vault_balances() {
+        let pools = extract_pools(
+            &BlockBuilder::new(300)
+                .transaction(with_vaults(swap(1)))
+                .build(),
+        );
+        assert_eq!(pools.len(), 1);
+        let pool = &pools[0];
+        assert_eq!(pool.address, pubkey(30));
+        assert_eq!(
+            (pool.coin_mint.as_str(), pool.coin_decimals),
+            (pubkey(18).as_str(), 9)
+        );
+        assert_eq!(
+            (pool.pc_mint.as_str(), pool.pc_decimals),
+            (pubkey(19).as_str(), 6)
+        );
+        assert_eq!(pool.source, PoolSource::Swap as i32);
+    }
+
+    #[test]
+    fn swap_without_vault_balances_is_not_registered() {
+        // 第一笔交易缺少金库余额，不登记；同一区块内之后带余额的交易照常登记
+        let only_coin =
+            swap(1).token_balance(&pubkey(31), &pubkey(18), &pubkey(13), 9, Some(1), Some(2));
+        assert!(extract_pools(&BlockBuilder::new(300).transaction(only_coin).build()).is_empty());
+
+        let block = BlockBuilder::new(300)
+            .transaction(swap(1))
+            .transaction(with_vaults(swap(2)))
+            .build();
+        let pools = extract_pools(&block);
+        assert_eq!(pools.len(), 1);
+        assert_eq!(pools[0].coin_mint, pubkey(18));
+    }
+}
@@ -0,0 +1,117 @@
+// 价格计算：原始数量按 decimals 换算后的比值
+
+use crate::pb::io::blockchain::v1::dex::pool::{Pool, PoolReserves};
+use crate::pb::io::blockchain::v1::dex::pricing::PricingSource;
+use crate::pb::io::blockchain::v1::dex::trade::Trade;
+use substreams::scalar::BigDecimal;
+use substreams::store::{StoreGet, StoreGetProto};
+
+pub const WSOL_MINT: &str = "So11111111111111111111111111111111111111112";
+pub const USDC_MINT: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+pub const USDT_MINT: &str = "Es9vMFrzaCERmJfrF4H2FYD4KCoNkY11McCe8BenwNYb";
+// SOL 美元价格的参考池：Raydium AMM v4 WSOL/USDC
+pub const SOL_USDC_POOL: &str = "58oQChx4yWmvKdwLLZzBi4ChoCc2fqCUWBkwMihLYQo2";
+
+// 价格保留的有效数字位数
+pub const PRICE_PRECISION: u64 = 24;
+
+pub fn to_decimal(raw: u128, decimals: u32) -> BigDecimal {
+    let amount = BigDecimal::try_from(raw.to_string()).unwrap_or_default();
+    BigDecimal::divide_by_decimals(amount, decimals as u64)
+}
+
+// 以 quote 计价的 base 价格：(quote / 10^quote_decimals) / (base / 10^base_decimals)
+pub fn ratio(
+    quote_raw: u128,
+    quote_decimals: u32,
+    base_raw: u128,
+    base_decimals: u32,
+) -> Option<BigDecimal> {
+    if base_raw == 0 {
+        return None;
+    }
+    let quote = to_decimal(quote_raw, quote_decimals);
+    let base = to_decimal(base_raw, base_decimals);
+    Some((quote / base).with_prec(PRICE_PRECISION))
+}
+
+// 单笔交易的成交价（pc / coin）与两侧成交量，均已按 decimals 换算
+pub struct TradePrice {
+    pub price: BigDecimal,
+    pub base_volume: BigDecimal,
+    pub quote_volume: BigDecimal,
+}
+
+pub fn trade_price(trade: &Trade, pool: &Pool) -> Option<TradePrice> {
+    let coin = trade.amount_of(&pool.coin_mint)?;
+    let pc = trade.amount_of(&pool.pc_mint)?;
+    Some(TradePrice {
+        price: ratio(pc, pool.pc_decimals, coin, pool.coin_decimals)?,
+        base_volume: to_decimal(coin, pool.coin_decimals),
+        quote_volume: to_decimal(pc, pool.pc_decimals),
+    })
+}
+
+// 参考池在 ordinal 处的 SOL 美元价格（USDC per WSOL）
+pub fn sol_usd_at(reserves: &StoreGetProto<PoolReserves>, ordinal: u64) -> Option<BigDecimal> {
+    let r = reserves.get_at(ordinal, SOL_USDC_POOL)?;
+    let price = BigDecimal::try_from(r.price.as_str()).ok()?;
+    if price == BigDecimal::zero() {
+        return None;
+    }
+    match (r.coin_mint.as_str(), r.pc_mint.as_str()) {
+        (WSOL_MINT, USDC_MINT) => Some(price),
+        (USDC_MINT, WSOL_MINT) => Some(BigDecimal::one() / price),
+        _ => None,
+    }
+}
+
+// 一个单位（已换算）的 mint 的美元价格：稳定币按 1 美元，WSOL 取参考价，其它 mint 无法直接定价
+pub fn usd_per_unit(
+    mint: &str,
+    sol_usd: Option<&BigDecimal>,
+) -> Option<(BigDecimal, PricingSource)> {
+    match mint {
+        USDC_MINT | USDT_MINT => Some((BigDecimal::one(), PricingSource::Stablecoin)),
+        WSOL_MINT => sol_usd.map(|p| (p.clone(), PricingSource::SolReference)),
+        _ => None,
+    }
+}
+
+pub struct UsdValue {
+    pub source: PricingSource,
+    pub volume: BigDecimal,
+    pub coin_price: Option<BigDecimal>,
+    pub pc_price: Option<BigDecimal>,
+}
+
+// 交易的美元成交额：优先取稳定币一侧，其次 WSOL 一侧；两侧都无法定价时返回 None
+pub fn usd_value(trade: &Trade, pool: &Pool, sol_usd: Option<&BigDecimal>) -> Option<UsdValue> {
+    let tp = trade_price(trade, pool)?;
+    let coin = usd_per_unit(&pool.coin_mint, sol_usd);
+    let pc = usd_per_unit(&pool.pc_mint, sol_usd);
+    let prefer_coin = match (&coin, &pc) {
+        (Some((_, PricingSource::Stablecoin)), _) => true,
+        (_, Some((_, PricingSource::Stablecoin))) => false,
+        _ => coin.is_some(),
+    };
+
+    let (volume, source) = if prefer_coin {
+        let (unit, source) = coin?;
+        (tp.base_volume.clone() * unit, source)
+    } else {
+        let (unit, source) = pc?;
+        (tp.quote_volume.clone() * unit, source)
+    };
+    let per_unit = |amount: &BigDecimal| {
+        (*amount != BigDecimal::zero())
+            .then(|| (volume.clone() / amount.clone()).with_prec(PRICE_PRECISION))
+    };
+
+    Some(UsdValue {
+        coin_price: per_unit(&tp.base_volume),
+        pc_price: per_unit(&tp.quote_volume),
+        volume: volume.with_prec(PRICE_PRECISION),
+        source,
+    })
+}
@@ -0,0 +1,58 @@
+// 交易的美元估值：稳定币一侧直接按 1 美元计，WSOL 一侧经 SOL/USDC 参考池在该笔交易执行时的价格换算。
+// 无法定价的交易标记为 priced = false，不给出估值。
+
+use crate::pb::io::blockchain::v1::dex::pool::{Pool, PoolReserves};
+use crate::pb::io::blockchain::v1::dex::pricing::{PricedTrade, PricedTrades, PricingSource};
+use crate::pb::io::blockchain::v1::dex::trade::TradeEvents;
+use crate::price;
+use substreams::store::{StoreGet, StoreGetProto};
+
+#[substreams::handlers::map]
+fn map_priced_trades(
+    trades: TradeEvents,
+    pools: StoreGetProto<Pool>,
+    reserves: StoreGetProto<PoolReserves>,
+) -> Result<PricedTrades, substreams::errors::Error> {
+    let mut priced_trades: Vec<PricedTrade> = Vec::new();
+
+    for event in trades.events {
+        let mut priced = PricedTrade::default();
+
+        if let Some(trade) = event.trade.as_ref() {
+            let sol_usd = price::sol_usd_at(&reserves, event.ordinal);
+            let value = pools
+                .get_last(&trade.pool_address)
+                .and_then(|pool| Some((price::usd_value(trade, &pool, sol_usd.as_ref())?, pool)));
+
+            if let Some((value, pool)) = value {
+                let price_of = |mint: &str| {
+                    if mint == pool.coin_mint {
+                        value.coin_price.as_ref()
+                    } else if mint == pool.pc_mint {
+                        value.pc_price.as_ref()
+                    } else {
+                        None
+                    }
+                    .map(|p| p.to_string())
+                    .unwrap_or_default()
+                };
+
+                priced.priced = true;
+                priced.source = value.source as i32;
+                priced.volume_usd = value.volume.to_string();
+                priced.token_a_price_usd = price_of(&trade.token_a_address);
+                priced.token_b_price_usd = price_of(&trade.token_b_address);
+                if value.source == PricingSource::SolReference {
+                    priced.sol_price_usd = sol_usd.map(|p| p.to_string()).unwrap_or_default();
+                }
+            }
+        }
+
+        priced.event = Some(event);
+        priced_trades.push(priced);
+    }
+
+    Ok(PricedTrades {
+        trades: priced_trades,
+    })
+}
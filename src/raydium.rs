@@ -0,0 +1,342 @@
+// Raydium AMM v4 指令解析
+
+use crate::pb::io::blockchain::v1::dex::trade::SwapMode;
+
+// 指令 tag（指令数据首字节）
+pub const DEPOSIT: u8 = 3;
+pub const WITHDRAW: u8 = 4;
+pub const SWAP_BASE_IN: u8 = 9;
+pub const SWAP_BASE_OUT: u8 = 11;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SwapInstruction {
+    // [9, amount_in: u64, minimum_amount_out: u64]
+    BaseIn {
+        amount_in: u64,
+        minimum_amount_out: u64,
+    },
+    // [11, max_amount_in: u64, amount_out: u64]
+    BaseOut {
+        max_amount_in: u64,
+        amount_out: u64,
+    },
+}
+
+impl SwapInstruction {
+    pub fn mode(&self) -> SwapMode {
+        match self {
+            SwapInstruction::BaseIn { .. } => SwapMode::BaseIn,
+            SwapInstruction::BaseOut { .. } => SwapMode::BaseOut,
+        }
+    }
+
+    // 用户指定的精确数量：base in 为 amount_in，base out 为 amount_out
+    pub fn amount_specified(&self) -> u64 {
+        match *self {
+            SwapInstruction::BaseIn { amount_in, .. } => amount_in,
+            SwapInstruction::BaseOut { amount_out, .. } => amount_out,
+        }
+    }
+
+    // 用户声明的滑点边界：base in 为 minimum_amount_out，base out 为 max_amount_in
+    pub fn slippage_limit(&self) -> u64 {
+        match *self {
+            SwapInstruction::BaseIn {
+                minimum_amount_out, ..
+            } => minimum_amount_out,
+            SwapInstruction::BaseOut { max_amount_in, .. } => max_amount_in,
+        }
+    }
+
+    // 剩余容忍度（bps）：实际成交量还能恶化多少比例才会触发回滚。
+    // base in: (out - min_out) / out；base out: (max_in - in) / in。
+    // 为负表示已越过边界（只会出现在失败交易中）。
+    pub fn unused_tolerance_bps(&self, realized: u64) -> i64 {
+        if realized == 0 {
+            return 0;
+        }
+        let realized = realized as i128;
+        let limit = self.slippage_limit() as i128;
+        let headroom = match self {
+            SwapInstruction::BaseIn { .. } => realized - limit,
+            SwapInstruction::BaseOut { .. } => limit - realized,
+        };
+        let bps = headroom * 10_000 / realized;
+        bps.clamp(i64::MIN as i128, i64::MAX as i128) as i64
+    }
+}
+
+// 解析 SwapBaseIn / SwapBaseOut 指令数据，其它指令返回 None
+pub fn decode_swap(data: &[u8]) -> Option<SwapInstruction> {
+    let (&tag, rest) = data.split_first()?;
+    if rest.len() < 16 {
+        return None;
+    }
+    let first = u64::from_le_bytes(rest[0..8].try_into().ok()?);
+    let second = u64::from_le_bytes(rest[8..16].try_into().ok()?);
+    match tag {
+        SWAP_BASE_IN => Some(SwapInstruction::BaseIn {
+            amount_in: first,
+            minimum_amount_out: second,
+        }),
+        SWAP_BASE_OUT => Some(SwapInstruction::BaseOut {
+            max_amount_in: first,
+            amount_out: second,
+        }),
+        _ => None,
+    }
+}
+
+// Swap 指令账户布局：18 个账户（含 amm_target_orders）或 17 个账户（新版省略 target_orders）
+//  0 token_program, 1 amm, 2 amm_authority, 3 amm_open_orders, [4 amm_target_orders],
+//  pool_coin_token_account, pool_pc_token_account, serum_program, serum_market,
+//  serum_bids, serum_asks, serum_event_queue, serum_coin_vault, serum_pc_vault,
+//  serum_vault_signer, user_source, user_destination, user_owner
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SwapAccounts {
+    pub amm: String,
+    pub amm_authority: String,
+    pub pool_coin_token_account: String,
+    pub pool_pc_token_account: String,
+    pub serum_market: String,
+    pub user_source: String,
+    pub user_destination: String,
+    pub user_owner: String,
+}
+
+impl SwapAccounts {
+    pub fn from_accounts(accounts: &[String]) -> Option<SwapAccounts> {
+        let offset = match accounts.len() {
+            18 => 1,
+            17 => 0,
+            _ => return None,
+        };
+        Some(SwapAccounts {
+            amm: accounts[1].clone(),
+            amm_authority: accounts[2].clone(),
+            pool_coin_token_account: accounts[4 + offset].clone(),
+            pool_pc_token_account: accounts[5 + offset].clone(),
+            serum_market: accounts[7 + offset].clone(),
+            user_source: accounts[14 + offset].clone(),
+            user_destination: accounts[15 + offset].clone(),
+            user_owner: accounts[16 + offset].clone(),
+        })
+    }
+}
+
+// AmmError 自定义错误码（raydium-amm program/src/error.rs 的枚举顺序）
+const AMM_ERRORS: &[&str] = &[
+    "AlreadyInUse",
+    "InvalidProgramAddress",
+    "ExpectedMint",
+    "ExpectedAccount",
+    "InvalidCoinVault",
+    "InvalidPCVault",
+    "InvalidTokenLP",
+    "InvalidDestTokenCoin",
+    "InvalidDestTokenPC",
+    "InvalidPoolMint",
+    "InvalidOpenOrders",
+    "InvalidSerumMarket",
+    "InvalidSerumProgram",
+    "InvalidTargetOrders",
+    "InvalidWithdrawQueue",
+    "InvalidTempLp",
+    "InvalidCoinMint",
+    "InvalidPCMint",
+    "InvalidOwner",
+    "InvalidSupply",
+    "InvalidDelegate",
+    "InvalidSignAccount",
+    "InvalidStatus",
+    "InvalidInstruction",
+    "WrongAccountsNumber",
+    "WithdrawTransferBusy",
+    "WithdrawQueueFull",
+    "WithdrawQueueEmpty",
+    "InvalidParamsSet",
+    "InvalidInput",
+    "ExceededSlippage",
+    "CalculationExRateFailure",
+    "CheckedSubOverflow",
+    "CheckedAddOverflow",
+    "CheckedMulOverflow",
+    "CheckedDivOverflow",
+    "CheckedEmptyFunds",
+    "CalcPnlError",
+    "InvalidSplTokenProgram",
+    "TakePnlError",
+    "InsufficientFunds",
+    "ConversionFailure",
+    "InvalidUserToken",
+    "InvalidSrmMint",
+    "InvalidSrmToken",
+    "TooManyOpenOrders",
+    "OrderAtSlotIsPlaced",
+    "InvalidSysProgramAddress",
+    "InvalidFee",
+    "RepeatCreateAmm",
+    "NotAllowZeroLP",
+    "InvalidCloseAuthority",
+    "InvalidFreezeAuthority",
+    "InvalidReferPCMint",
+    "InvalidConfigAccount",
+    "RepeatCreateConfigAccount",
+    "MarketLotSizeIsTooLarge",
+    "InitLpAmountTooLess",
+    "UnknownAmmError",
+];
+
+pub fn amm_error_name(code: u32) -> Option<&'static str> {
+    AMM_ERRORS.get(code as usize).copied()
+}
+
+// ray_log：Raydium 在 "Program log: ray_log: <base64>" 中输出的 bincode 结构
+pub const LOG_SWAP_BASE_IN: u8 = 3;
+pub const LOG_SWAP_BASE_OUT: u8 = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RayLog {
+    SwapBaseIn {
+        amount_in: u64,
+        minimum_out: u64,
+        direction: u64,
+        user_source: u64,
+        pool_coin: u64,
+        pool_pc: u64,
+        out_amount: u64,
+    },
+    SwapBaseOut {
+        max_in: u64,
+        amount_out: u64,
+        direction: u64,
+        user_source: u64,
+        pool_coin: u64,
+        pool_pc: u64,
+        deduct_in: u64,
+    },
+}
+
+impl RayLog {
+    // 实际成交数量：base in 为实际得到的 out，base out 为实际付出的 in
+    pub fn realized_amount(&self) -> u64 {
+        match *self {
+            RayLog::SwapBaseIn { out_amount, .. } => out_amount,
+            RayLog::SwapBaseOut { deduct_in, .. } => deduct_in,
+        }
+    }
+
+    // 本条指令的用户成交数量：(付出, 得到)
+    pub fn user_amounts(&self) -> (u64, u64) {
+        match *self {
+            RayLog::SwapBaseIn {
+                amount_in,
+                out_amount,
+                ..
+            } => (amount_in, out_amount),
+            RayLog::SwapBaseOut {
+                amount_out,
+                deduct_in,
+                ..
+            } => (deduct_in, amount_out),
+        }
+    }
+}
+
+pub fn decode_ray_log(payload: &str) -> Option<RayLog> {
+    use base64::Engine;
+
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(payload)
+        .ok()?;
+    let (&log_type, rest) = bytes.split_first()?;
+    let field = |i: usize| -> Option<u64> {
+        Some(u64::from_le_bytes(
+            rest.get(i * 8..i * 8 + 8)?.try_into().ok()?,
+        ))
+    };
+
+    match log_type {
+        LOG_SWAP_BASE_IN => Some(RayLog::SwapBaseIn {
+            amount_in: field(0)?,
+            minimum_out: field(1)?,
+            direction: field(2)?,
+            user_source: field(3)?,
+            pool_coin: field(4)?,
+            pool_pc: field(5)?,
+            out_amount: field(6)?,
+        }),
+        LOG_SWAP_BASE_OUT => Some(RayLog::SwapBaseOut {
+            max_in: field(0)?,
+            amount_out: field(1)?,
+            direction: field(2)?,
+            user_source: field(3)?,
+            pool_coin: field(4)?,
+            pool_pc: field(5)?,
+            deduct_in: field(6)?,
+        }),
+        _ => None,
+    }
+}
+
+// AMM v4 交易手续费 25 bps
+pub const SWAP_FEE_NUMERATOR: u128 = 25;
+pub const SWAP_FEE_DENOMINATOR: u128 = 10_000;
+
+// 恒定乘积报价（扣除手续费后）：out = in' * reserve_out / (reserve_in + in')
+pub fn constant_product_out(amount_in: u128, reserve_in: u128, reserve_out: u128) -> u128 {
+    let amount_in = amount_in * (SWAP_FEE_DENOMINATOR - SWAP_FEE_NUMERATOR) / SWAP_FEE_DENOMINATOR;
+    if reserve_in + amount_in == 0 {
+        return 0;
+    }
+    amount_in * reserve_out / (reserve_in + amount_in)
+}
+
+// Initialize2：[1, nonce: u8, open_time: u64, init_pc_amount: u64, init_coin_amount: u64]
+pub const INITIALIZE2: u8 = 1;
+
+// Initialize2 指令账户布局
+//  0 token_program, 1 associated_token_program, 2 system_program, 3 rent, 4 amm,
+//  5 amm_authority, 6 amm_open_orders, 7 lp_mint, 8 coin_mint, 9 pc_mint,
+//  10 pool_coin_token_account, 11 pool_pc_token_account, 12 amm_target_orders,
+//  13 amm_config, 14 create_fee_destination, 15 market_program, 16 market,
+//  17 user_wallet, 18 user_token_coin, 19 user_token_pc, 20 user_token_lp
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct InitializeAccounts {
+    pub amm: String,
+    pub lp_mint: String,
+    pub coin_mint: String,
+    pub pc_mint: String,
+    pub pool_coin_token_account: String,
+    pub pool_pc_token_account: String,
+    pub market: String,
+}
+
+impl InitializeAccounts {
+    pub fn from_accounts(accounts: &[String]) -> Option<InitializeAccounts> {
+        if accounts.len() < 17 {
+            return None;
+        }
+        Some(InitializeAccounts {
+            amm: accounts[4].clone(),
+            lp_mint: accounts[7].clone(),
+            coin_mint: accounts[8].clone(),
+            pc_mint: accounts[9].clone(),
+            pool_coin_token_account: accounts[10].clone(),
+            pool_pc_token_account: accounts[11].clone(),
+            market: accounts[16].clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn amm_error_names_follow_enum_order() {
+        assert_eq!(amm_error_name(0), Some("AlreadyInUse"));
+        assert_eq!(amm_error_name(0x1e), Some("ExceededSlippage"));
+        assert_eq!(amm_error_name(u32::MAX), None);
+    }
+}
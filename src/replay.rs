@@ -0,0 +1,112 @@
+// 离线回放：从本地文件读取 sf.solana.type.v1.Block，按 substreams 中的顺序执行
+// map_pools → store_pools → map_ray_swap，无需连接 endpoint 即可调试指定 slot
+
+use crate::pb::io::blockchain::v1::dex::pool::Pool;
+use crate::pb::io::blockchain::v1::dex::trade::TradeEvents;
+use crate::{extract_pools, extract_trade_events};
+use prost::Message;
+use std::collections::HashMap;
+use substreams::errors::Error;
+use substreams_solana::pb::sf::solana::r#type::v1::Block;
+
+const DBIN_MAGIC: &[u8] = b"dbin";
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+// merged-blocks 文件中每条记录为 sf.bstream.v1.Block，只解出需要的字段
+#[derive(Clone, PartialEq, Message)]
+struct BstreamBlock {
+    #[prost(uint64, tag = "1")]
+    number: u64,
+    #[prost(bytes = "vec", tag = "8")]
+    payload_buffer: Vec<u8>,
+    #[prost(message, optional, tag = "11")]
+    payload: Option<prost_types::Any>,
+}
+
+// 支持两种格式：
+// - merged-blocks（dbin）：Firehose 的 100 区块包，需先用 `zstd -d` 解压
+// - 长度前缀（varint）拼接的 Block 消息
+pub fn read_blocks(bytes: &[u8]) -> Result<Vec<Block>, Error> {
+    if bytes.starts_with(ZSTD_MAGIC) {
+        return Err(Error::msg(
+            "zstd compressed merged-blocks, decompress with `zstd -d` first",
+        ));
+    }
+    if bytes.starts_with(DBIN_MAGIC) {
+        read_dbin(bytes)
+    } else {
+        read_length_delimited(bytes)
+    }
+}
+
+fn read_length_delimited(mut bytes: &[u8]) -> Result<Vec<Block>, Error> {
+    let mut blocks: Vec<Block> = Vec::new();
+    while !bytes.is_empty() {
+        let block = Block::decode_length_delimited(&mut bytes)
+            .map_err(|e| Error::msg(format!("block #{}: {}", blocks.len(), e)))?;
+        blocks.push(block);
+    }
+    Ok(blocks)
+}
+
+// dbin 头：v0 为 "dbin" + 版本(1 字节) + 内容类型(3 字节) + 内容版本(2 字节)；
+// v1 为 "dbin" + 版本(1 字节) + 内容类型长度(u16 BE) + 内容类型。
+// 之后每条记录为 长度(u32 BE) + 消息
+fn read_dbin(bytes: &[u8]) -> Result<Vec<Block>, Error> {
+    let truncated = || Error::msg("truncated dbin file");
+    let version = *bytes.get(4).ok_or_else(truncated)?;
+    let mut offset = match version {
+        0 => 10,
+        1 => {
+            let len = bytes.get(5..7).ok_or_else(truncated)?;
+            7 + u16::from_be_bytes([len[0], len[1]]) as usize
+        }
+        v => return Err(Error::msg(format!("unsupported dbin version {}", v))),
+    };
+
+    let mut blocks: Vec<Block> = Vec::new();
+    while offset < bytes.len() {
+        let len = bytes.get(offset..offset + 4).ok_or_else(truncated)?;
+        let len = u32::from_be_bytes([len[0], len[1], len[2], len[3]]) as usize;
+        offset += 4;
+        let record = bytes.get(offset..offset + len).ok_or_else(truncated)?;
+        offset += len;
+
+        let wrapper = BstreamBlock::decode(record)
+            .map_err(|e| Error::msg(format!("bstream block #{}: {}", blocks.len(), e)))?;
+        let payload = match wrapper.payload.as_ref() {
+            Some(any) => any.value.as_slice(),
+            None => wrapper.payload_buffer.as_slice(),
+        };
+        let block = Block::decode(payload)
+            .map_err(|e| Error::msg(format!("solana block {}: {}", wrapper.number, e)))?;
+        blocks.push(block);
+    }
+    Ok(blocks)
+}
+
+// 内存中的 store_pools：与 set_if_not_exists 语义一致，只保留第一次见到的池子信息
+#[derive(Default)]
+pub struct Replay {
+    pools: HashMap<String, Pool>,
+}
+
+impl Replay {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // 区块需按 slot 顺序依次传入；同一区块新登记的池子对本区块的 swap 可见
+    pub fn process(&mut self, block: &Block) -> TradeEvents {
+        self.register_pools(block);
+        TradeEvents {
+            events: extract_trade_events(block, &self.pools),
+        }
+    }
+
+    pub fn register_pools(&mut self, block: &Block) {
+        for pool in extract_pools(block) {
+            self.pools.entry(pool.address.clone()).or_insert(pool);
+        }
+    }
+}
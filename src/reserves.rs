@@ -0,0 +1,190 @@
+// 池子最新储备与现货价格：取每笔 swap 及 deposit / withdraw 之后的金库余额，
+// 按 ordinal 写入保证同一区块内按执行顺序覆盖
+
+use crate::envelope::block_info;
+use crate::pb::io::blockchain::v1::dex::pool::{Pool, PoolReserves, PoolReservesUpdates};
+use crate::pb::io::blockchain::v1::dex::trade::TradeEvents;
+use crate::{instructions, price, raydium, RAYDIUM};
+use substreams::store::{StoreGet, StoreGetProto, StoreNew, StoreSet, StoreSetProto};
+use substreams_solana::pb::sf::solana::r#type::v1::{Block, TransactionStatusMeta};
+
+// 已登记池子上的 deposit / withdraw：取交易结束时两个金库的余额
+#[substreams::handlers::map]
+fn map_liquidity_reserves(
+    block: Block,
+    pools: StoreGetProto<Pool>,
+) -> Result<PoolReservesUpdates, substreams::errors::Error> {
+    let mut updates: Vec<PoolReserves> = Vec::new();
+    let c_block = block_info(&block);
+
+    for (tx_index, tx) in block.transactions.iter().enumerate() {
+        let Some(meta) = tx.meta.as_ref() else {
+            continue;
+        };
+        if meta.err.is_some() {
+            continue;
+        }
+        let Some(message) = tx.transaction.as_ref().and_then(|t| t.message.as_ref()) else {
+            continue;
+        };
+        let account_keys: Vec<String> = tx
+            .resolved_accounts()
+            .iter()
+            .map(|k| bs58::encode(k).into_string())
+            .collect();
+
+        for ix in instructions::flatten(message, meta) {
+            if account_keys
+                .get(ix.program_id_index as usize)
+                .map(String::as_str)
+                != Some(RAYDIUM)
+            {
+                continue;
+            }
+            if !matches!(
+                ix.data.first(),
+                Some(&raydium::DEPOSIT) | Some(&raydium::WITHDRAW)
+            ) {
+                continue;
+            }
+            // deposit / withdraw 布局中 amm 均为 index 1，金库地址取自注册表
+            let Some(pool) = ix
+                .accounts
+                .get(1)
+                .and_then(|&i| account_keys.get(i as usize))
+                .and_then(|amm| pools.get_last(amm))
+            else {
+                continue;
+            };
+            let (Some(coin_reserve), Some(pc_reserve)) = (
+                post_balance(meta, &account_keys, &pool.coin_vault),
+                post_balance(meta, &account_keys, &pool.pc_vault),
+            ) else {
+                continue;
+            };
+
+            let signature = tx
+                .transaction
+                .as_ref()
+                .and_then(|t| t.signatures.first())
+                .map(|sig| bs58::encode(sig).into_string())
+                .unwrap_or_default();
+            let ordinal = instructions::ordinal(tx_index as u32, ix.index, ix.inner_index);
+            let mut reserves = reserves(&pool, coin_reserve, pc_reserve);
+            reserves.slot = c_block.slot;
+            reserves.timestamp = c_block.timestamp;
+            reserves.signature = signature;
+            reserves.ordinal = ordinal;
+            updates.push(reserves);
+        }
+    }
+
+    Ok(PoolReservesUpdates { reserves: updates })
+}
+
+#[substreams::handlers::store]
+fn store_pool_reserves(
+    trades: TradeEvents,
+    liquidity: PoolReservesUpdates,
+    pools: StoreGetProto<Pool>,
+    store: StoreSetProto<PoolReserves>,
+) {
+    for update in reserve_updates(&trades, &liquidity, &pools) {
+        store.set(
+            update.reserves.ordinal,
+            &update.pool.address,
+            &update.reserves,
+        );
+    }
+}
+
+pub struct ReserveUpdate {
+    pub pool: Pool,
+    pub reserves: PoolReserves,
+}
+
+// 本区块内所有储备变化（swap 与 deposit / withdraw），按 ordinal 排序
+pub fn reserve_updates(
+    trades: &TradeEvents,
+    liquidity: &PoolReservesUpdates,
+    pools: &StoreGetProto<Pool>,
+) -> Vec<ReserveUpdate> {
+    let mut updates: Vec<ReserveUpdate> = Vec::new();
+
+    for event in &trades.events {
+        let Some(trade) = event.trade.as_ref() else {
+            continue;
+        };
+        let Some(pool) = pools.get_last(&trade.pool_address) else {
+            continue;
+        };
+        let (Some(coin_reserve), Some(pc_reserve)) = (
+            trade.vault_post_amount(&pool.coin_mint),
+            trade.vault_post_amount(&pool.pc_mint),
+        ) else {
+            continue;
+        };
+
+        let mut reserves = reserves(&pool, coin_reserve, pc_reserve);
+        reserves.slot = event.block.as_ref().map(|b| b.slot).unwrap_or_default();
+        reserves.timestamp = event
+            .block
+            .as_ref()
+            .map(|b| b.timestamp)
+            .unwrap_or_default();
+        reserves.signature = event
+            .transaction
+            .as_ref()
+            .map(|t| t.signature.clone())
+            .unwrap_or_default();
+        reserves.ordinal = event.ordinal;
+        updates.push(ReserveUpdate { pool, reserves });
+    }
+
+    for reserves in &liquidity.reserves {
+        if let Some(pool) = pools.get_last(&reserves.pool_address) {
+            updates.push(ReserveUpdate {
+                pool,
+                reserves: reserves.clone(),
+            });
+        }
+    }
+
+    updates.sort_by_key(|u| u.reserves.ordinal);
+    updates
+}
+
+fn reserves(pool: &Pool, coin_reserve: u128, pc_reserve: u128) -> PoolReserves {
+    PoolReserves {
+        pool_address: pool.address.clone(),
+        coin_mint: pool.coin_mint.clone(),
+        pc_mint: pool.pc_mint.clone(),
+        coin_reserve: coin_reserve.to_string(),
+        pc_reserve: pc_reserve.to_string(),
+        price: price::ratio(
+            pc_reserve,
+            pool.pc_decimals,
+            coin_reserve,
+            pool.coin_decimals,
+        )
+        .map(|p| p.to_string())
+        .unwrap_or_default(),
+        ..Default::default()
+    }
+}
+
+fn post_balance(
+    meta: &TransactionStatusMeta,
+    account_keys: &[String],
+    vault: &str,
+) -> Option<u128> {
+    let idx = account_keys.iter().position(|k| k == vault)? as u32;
+    meta.post_token_balances
+        .iter()
+        .find(|b| b.account_index == idx)?
+        .ui_token_amount
+        .as_ref()?
+        .amount
+        .parse()
+        .ok()
+}
@@ -0,0 +1,244 @@
+// 区块内 Raydium 三明治攻击识别
+//
+// 同一池子内按交易序号排序，寻找 (front-run, victim, back-run)：
+// 攻击者在 front-run 与受害者同向卖出 X 买入 Y，在 back-run 反向卖出 Y 换回 X，
+// 受害者的交易夹在两者之间。
+//
+// 利润统一以池子的 pc mint（B 侧）计：攻击者在两笔交易中 pc 的净变化，加上 coin 的净变化
+// （back-run 未完全平仓时的剩余或超卖部分）按 back-run 成交价折算成 pc。
+
+use crate::pb::io::blockchain::v1::dex::mev::{Sandwich, Sandwiches};
+use crate::pb::io::blockchain::v1::dex::trade::{Trade, TradeEvent, TradeEvents};
+use crate::raydium;
+use std::collections::BTreeMap;
+
+#[substreams::handlers::map]
+fn map_sandwiches(trades: TradeEvents) -> Result<Sandwiches, substreams::errors::Error> {
+    Ok(Sandwiches {
+        sandwiches: detect_sandwiches(&trades.events),
+    })
+}
+
+fn detect_sandwiches(events: &[TradeEvent]) -> Vec<Sandwich> {
+    let mut sandwiches: Vec<Sandwich> = Vec::new();
+
+    // 按池子分组（BTreeMap 保证输出顺序确定）
+    let mut by_pool: BTreeMap<&str, Vec<&TradeEvent>> = BTreeMap::new();
+    for event in events {
+        let Some(trade) = event.trade.as_ref() else {
+            continue;
+        };
+        if trade.pool_address.is_empty() || trade.trader().is_empty() {
+            continue;
+        }
+        by_pool.entry(&trade.pool_address).or_default().push(event);
+    }
+
+    for (pool, mut events) in by_pool {
+        events.sort_by_key(|e| e.execution_order());
+
+        for (i, front) in events.iter().enumerate() {
+            let front_trade = front.trade.as_ref().unwrap();
+            let attacker = front_trade.trader();
+
+            // 最近的一笔由同一攻击者发起、方向相反的 back-run
+            let Some(k) = (i + 1..events.len()).find(|&k| {
+                let back = events[k].trade.as_ref().unwrap();
+                back.trader() == attacker
+                    && events[k].tx_index() > front.tx_index()
+                    && back.sold_mint() == front_trade.bought_mint()
+                    && back.bought_mint() == front_trade.sold_mint()
+            }) else {
+                continue;
+            };
+            let back = events[k];
+
+            for victim in &events[i + 1..k] {
+                let victim_trade = victim.trade.as_ref().unwrap();
+                if victim_trade.trader() == attacker
+                    || victim.tx_index() <= front.tx_index()
+                    || victim.tx_index() >= back.tx_index()
+                    || victim_trade.sold_mint() != front_trade.sold_mint()
+                {
+                    continue;
+                }
+                sandwiches.push(build_sandwich(pool, front, victim, back));
+            }
+        }
+    }
+
+    sandwiches
+}
+
+fn build_sandwich(
+    pool: &str,
+    front: &TradeEvent,
+    victim: &TradeEvent,
+    back: &TradeEvent,
+) -> Sandwich {
+    let front_trade = front.trade.as_ref().unwrap();
+    let victim_trade = victim.trade.as_ref().unwrap();
+    let back_trade = back.trade.as_ref().unwrap();
+
+    // 攻击者利润：以池子的 pc mint 计
+    let pc_mint = front_trade.token_b_address.as_str();
+    let coin_mint = front_trade.token_a_address.as_str();
+    let profit = match (
+        net_change(front_trade, back_trade, pc_mint),
+        net_change(front_trade, back_trade, coin_mint),
+        back_trade.amount_of(pc_mint),
+        back_trade.amount_of(coin_mint),
+    ) {
+        (Some(pc), Some(coin), Some(back_pc), Some(back_coin)) if back_coin > 0 => {
+            (pc + coin * back_pc as i128 / back_coin as i128).to_string()
+        }
+        _ => String::new(),
+    };
+
+    // 受害者损失：front-run 之前的金库余额下，同样的输入本应得到的数量减去实际得到的数量
+    let victim_in_mint = victim_trade.sold_mint();
+    let victim_out_mint = victim_trade.bought_mint();
+    let loss = match (
+        victim_trade.amount_sold(),
+        victim_trade.amount_bought(),
+        front_trade.vault_pre_amount(victim_in_mint),
+        front_trade.vault_pre_amount(victim_out_mint),
+    ) {
+        (Some(amount_in), Some(amount_out), Some(reserve_in), Some(reserve_out)) => {
+            let expected = raydium::constant_product_out(amount_in, reserve_in, reserve_out);
+            (expected as i128 - amount_out as i128).to_string()
+        }
+        _ => String::new(),
+    };
+
+    Sandwich {
+        block: front.block.clone(),
+        pool_address: pool.to_string(),
+        attacker_address: front_trade.trader().to_string(),
+        victim_address: victim_trade.trader().to_string(),
+        front_run: Some(front.clone()),
+        victim: Some(victim.clone()),
+        back_run: Some(back.clone()),
+        profit_mint: pc_mint.to_string(),
+        profit_amount: profit,
+        victim_loss_mint: victim_out_mint.to_string(),
+        victim_loss_amount: loss,
+    }
+}
+
+// 攻击者在 front-run 与 back-run 中某个 mint 的净变化（买入为正、卖出为负）
+fn net_change(front: &Trade, back: &Trade, mint: &str) -> Option<i128> {
+    let change = |trade: &Trade| -> Option<i128> {
+        if mint == trade.bought_mint() {
+            trade.amount_bought().map(|a| a as i128)
+        } else if mint == trade.sold_mint() {
+            trade.amount_sold().map(|a| -(a as i128))
+        } else {
+            Some(0)
+        }
+    };
+    Some(change(front)? + change(back)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pb::io::blockchain::v1::dex::trade::TradeAmounts;
+    use crate::pb::io::chainstream::v1::common::Transaction;
+
+    const POOL: &str = "pool";
+    const COIN: &str = "coin";
+    const PC: &str = "pc";
+
+    // 池子 coin / pc 储备 1_000_000 / 2_000_000；sells_coin 为 true 时卖出 coin 买入 pc
+    fn swap(tx_index: u32, trader: &str, sells_coin: bool, paid: u64, received: u64) -> TradeEvent {
+        TradeEvent {
+            transaction: Some(Transaction {
+                index: tx_index,
+                ..Default::default()
+            }),
+            trade: Some(Trade {
+                pool_address: POOL.to_string(),
+                token_a_address: COIN.to_string(),
+                token_b_address: PC.to_string(),
+                was_original_direction: sells_coin,
+                user_a_account_owner_address: trader.to_string(),
+                amounts: Some(TradeAmounts {
+                    user_a_amount: Some(paid),
+                    user_b_amount: Some(received),
+                    vault_a_pre_amount: Some(1_000_000),
+                    vault_b_pre_amount: Some(2_000_000),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn coin_side_attack_profit_is_in_pc() {
+        // 攻击者卖 100 coin 得 200 pc，受害者同向卖出，back-run 用 200 pc 换回 110 coin
+        let sandwiches = detect_sandwiches(&[
+            swap(0, "attacker", true, 100, 200),
+            swap(1, "victim", true, 50, 95),
+            swap(2, "attacker", false, 200, 110),
+        ]);
+        assert_eq!(sandwiches.len(), 1);
+        let sandwich = &sandwiches[0];
+        assert_eq!(sandwich.attacker_address, "attacker");
+        assert_eq!(sandwich.victim_address, "victim");
+        assert_eq!(sandwich.profit_mint, PC);
+        // pc 净变化 0，多出的 10 coin 按 back-run 价格 200 / 110 折算
+        assert_eq!(sandwich.profit_amount, "18");
+        assert_eq!(sandwich.victim_loss_mint, PC);
+    }
+
+    #[test]
+    fn pc_side_attack_profit_is_in_pc() {
+        let sandwiches = detect_sandwiches(&[
+            swap(0, "attacker", false, 1_000, 500),
+            swap(1, "victim", false, 300, 140),
+            swap(2, "attacker", true, 500, 1_050),
+        ]);
+        assert_eq!(sandwiches.len(), 1);
+        assert_eq!(sandwiches[0].profit_mint, PC);
+        assert_eq!(sandwiches[0].profit_amount, "50");
+    }
+
+    #[test]
+    fn partial_unwind_values_leftover_coin() {
+        // back-run 只卖出 400 coin：pc 净变化 -160，剩余 100 coin 按 840 / 400 折算为 210
+        let sandwiches = detect_sandwiches(&[
+            swap(0, "attacker", false, 1_000, 500),
+            swap(1, "victim", false, 300, 140),
+            swap(2, "attacker", true, 400, 840),
+        ]);
+        assert_eq!(sandwiches[0].profit_amount, "50");
+    }
+
+    #[test]
+    fn non_matching_triples_are_ignored() {
+        // back-run 由其它地址发起
+        assert!(detect_sandwiches(&[
+            swap(0, "attacker", true, 100, 200),
+            swap(1, "victim", true, 50, 95),
+            swap(2, "other", false, 200, 110),
+        ])
+        .is_empty());
+        // 中间交易与 front-run 方向相反
+        assert!(detect_sandwiches(&[
+            swap(0, "attacker", true, 100, 200),
+            swap(1, "victim", false, 95, 50),
+            swap(2, "attacker", false, 200, 110),
+        ])
+        .is_empty());
+        // 第三笔与 front-run 同向，不是平仓
+        assert!(detect_sandwiches(&[
+            swap(0, "attacker", true, 100, 200),
+            swap(1, "victim", true, 50, 95),
+            swap(2, "attacker", true, 100, 180),
+        ])
+        .is_empty());
+    }
+}
@@ -0,0 +1,321 @@
+// 按天（UTC，取自 block_time）统计每个池子、每个 mint 的成交量、笔数、买卖笔数与独立交易者数
+//
+// key 规则：池子 pool:<day>:<池子>，mint mint:<day>:<mint>，day 为 unix 时间 / 86400。
+// 独立交易者：store_daily_traders 以 set_if_not_exists 记录 <前缀>:<交易者>，
+// store_daily_counts 读取其 deltas，仅对新建的 key 计数。
+// 统计只需要当天的 key，否则按天与按交易者的 key 会无限增长：store_stats_day 记录最近一个有成交的区块
+// 所在的天，三个 store 读取其 deltas，天数变化时删除旧一天的前缀。key 只会在有成交的区块写入，
+// 因此旧值之前的天早已删除；中间隔了多天没有成交也不会遗留。
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::pb::io::blockchain::v1::dex::pool::Pool;
+use crate::pb::io::blockchain::v1::dex::stats::{MintDayStat, PoolDayStat, PoolStats, QuoteVolume};
+use crate::pb::io::blockchain::v1::dex::trade::TradeEvents;
+use crate::price::{self, TradePrice};
+use substreams::pb::substreams::store_delta::Operation;
+use substreams::store::{
+    DeltaInt64, Deltas, StoreAdd, StoreAddBigDecimal, StoreAddInt64, StoreDelete, StoreGet,
+    StoreGetBigDecimal, StoreGetInt64, StoreGetProto, StoreNew, StoreSet, StoreSetIfNotExists,
+    StoreSetIfNotExistsInt64, StoreSetInt64,
+};
+
+const SECONDS_PER_DAY: i64 = 86_400;
+
+// "day" -> 最近一个有成交的区块所在的天
+#[substreams::handlers::store]
+fn store_stats_day(trades: TradeEvents, store: StoreSetInt64) {
+    // 区块内所有成交共享 block_time，以第一笔成交的日期为当天；没有成交的区块不做处理
+    if let Some(block) = trades.events.iter().find_map(|e| e.block.as_ref()) {
+        store.set(0, "day", &block.timestamp.div_euclid(SECONDS_PER_DAY));
+    }
+}
+
+#[substreams::handlers::store]
+fn store_daily_traders(
+    trades: TradeEvents,
+    pools: StoreGetProto<Pool>,
+    days: Deltas<DeltaInt64>,
+    store: StoreSetIfNotExistsInt64,
+) {
+    prune_stale_days(&days, &store);
+    for t in day_trades(&trades, &pools) {
+        let pool = pool_key(t.day, &t.pool.address);
+        let coin = mint_key(t.day, &t.pool.coin_mint);
+        let pc = mint_key(t.day, &t.pool.pc_mint);
+        for prefix in [pool, coin, pc] {
+            store.set_if_not_exists(t.ordinal, format!("{}:{}", prefix, t.trader), &1);
+        }
+    }
+}
+
+#[substreams::handlers::store]
+fn store_daily_counts(
+    trades: TradeEvents,
+    pools: StoreGetProto<Pool>,
+    traders: Deltas<DeltaInt64>,
+    days: Deltas<DeltaInt64>,
+    store: StoreAddInt64,
+) {
+    prune_stale_days(&days, &store);
+    for t in day_trades(&trades, &pools) {
+        let pool = pool_key(t.day, &t.pool.address);
+        let coin = mint_key(t.day, &t.pool.coin_mint);
+        let pc = mint_key(t.day, &t.pool.pc_mint);
+
+        // 池子的买卖以 coin 为准；mint 的买卖以该 mint 为准
+        let (pool_side, coin_side, pc_side) = if t.bought_coin {
+            ("buys", "buys", "sells")
+        } else {
+            ("sells", "sells", "buys")
+        };
+        for (prefix, side) in [(pool, pool_side), (coin, coin_side), (pc, pc_side)] {
+            store.add(t.ordinal, format!("{}:trades", prefix), 1);
+            store.add(t.ordinal, format!("{}:{}", prefix, side), 1);
+        }
+    }
+
+    for delta in traders.iter().filter(|d| d.operation == Operation::Create) {
+        if let Some((prefix, _trader)) = delta.key.rsplit_once(':') {
+            store.add(delta.ordinal, format!("{}:traders", prefix), 1);
+        }
+    }
+}
+
+#[substreams::handlers::store]
+fn store_daily_volume(
+    trades: TradeEvents,
+    pools: StoreGetProto<Pool>,
+    days: Deltas<DeltaInt64>,
+    store: StoreAddBigDecimal,
+) {
+    prune_stale_days(&days, &store);
+    for t in day_trades(&trades, &pools) {
+        let pool = pool_key(t.day, &t.pool.address);
+        let coin = mint_key(t.day, &t.pool.coin_mint);
+        let pc = mint_key(t.day, &t.pool.pc_mint);
+        let base = t.price.base_volume;
+        let quote = t.price.quote_volume;
+
+        store.add(t.ordinal, format!("{}:base", pool), &base);
+        store.add(t.ordinal, format!("{}:quote", pool), &quote);
+        store.add(t.ordinal, format!("{}:volume", coin), &base);
+        store.add(
+            t.ordinal,
+            format!("{}:quote:{}", coin, t.pool.pc_mint),
+            &quote,
+        );
+        store.add(t.ordinal, format!("{}:volume", pc), &quote);
+        store.add(
+            t.ordinal,
+            format!("{}:quote:{}", pc, t.pool.coin_mint),
+            &base,
+        );
+    }
+}
+
+#[substreams::handlers::map]
+fn map_pool_stats(
+    trades: TradeEvents,
+    pools: StoreGetProto<Pool>,
+    counts: StoreGetInt64,
+    volumes: StoreGetBigDecimal,
+) -> Result<PoolStats, substreams::errors::Error> {
+    // 本区块涉及的 (day, 池子) 与 (day, mint) -> 对手方 mint
+    let mut touched_pools: BTreeMap<(i64, String), Pool> = BTreeMap::new();
+    let mut touched_mints: BTreeMap<(i64, String), BTreeSet<String>> = BTreeMap::new();
+    for t in day_trades(&trades, &pools) {
+        touched_mints
+            .entry((t.day, t.pool.coin_mint.clone()))
+            .or_default()
+            .insert(t.pool.pc_mint.clone());
+        touched_mints
+            .entry((t.day, t.pool.pc_mint.clone()))
+            .or_default()
+            .insert(t.pool.coin_mint.clone());
+        touched_pools.insert((t.day, t.pool.address.clone()), t.pool);
+    }
+
+    let count = |key: String| counts.get_last(key).unwrap_or_default().max(0) as u64;
+    let volume = |key: String| volumes.get_last(key).unwrap_or_default().to_string();
+
+    let pool_stats = touched_pools
+        .into_iter()
+        .map(|((day, address), pool)| {
+            let key = pool_key(day, &address);
+            PoolDayStat {
+                pool_address: address,
+                base_mint: pool.coin_mint,
+                quote_mint: pool.pc_mint,
+                date: date(day),
+                day_start: day * SECONDS_PER_DAY,
+                base_volume: volume(format!("{}:base", key)),
+                quote_volume: volume(format!("{}:quote", key)),
+                trade_count: count(format!("{}:trades", key)),
+                buy_count: count(format!("{}:buys", key)),
+                sell_count: count(format!("{}:sells", key)),
+                unique_traders: count(format!("{}:traders", key)),
+            }
+        })
+        .collect();
+
+    let mint_stats = touched_mints
+        .into_iter()
+        .map(|((day, mint), counterparts)| {
+            let key = mint_key(day, &mint);
+            MintDayStat {
+                date: date(day),
+                day_start: day * SECONDS_PER_DAY,
+                volume: volume(format!("{}:volume", key)),
+                trade_count: count(format!("{}:trades", key)),
+                buy_count: count(format!("{}:buys", key)),
+                sell_count: count(format!("{}:sells", key)),
+                unique_traders: count(format!("{}:traders", key)),
+                quote_volumes: counterparts
+                    .into_iter()
+                    .map(|quote_mint| QuoteVolume {
+                        volume: volume(format!("{}:quote:{}", key, quote_mint)),
+                        quote_mint,
+                    })
+                    .collect(),
+                mint,
+            }
+        })
+        .collect();
+
+    Ok(PoolStats {
+        pools: pool_stats,
+        mints: mint_stats,
+    })
+}
+
+// 可统计的交易：池子已登记且两侧数量可解析
+struct DayTrade<'a> {
+    day: i64,
+    ordinal: u64,
+    pool: Pool,
+    trader: &'a str,
+    price: TradePrice,
+    bought_coin: bool,
+}
+
+fn day_trades<'a>(trades: &'a TradeEvents, pools: &StoreGetProto<Pool>) -> Vec<DayTrade<'a>> {
+    trades
+        .events
+        .iter()
+        .filter_map(|event| {
+            let trade = event.trade.as_ref()?;
+            let block = event.block.as_ref()?;
+            let pool = pools.get_last(&trade.pool_address)?;
+            let price = price::trade_price(trade, &pool)?;
+            Some(DayTrade {
+                day: block.timestamp.div_euclid(SECONDS_PER_DAY),
+                ordinal: event.ordinal,
+                bought_coin: trade.bought_mint() == pool.coin_mint,
+                trader: trade.trader(),
+                pool,
+                price,
+            })
+        })
+        .collect()
+}
+
+fn prune_stale_days(days: &Deltas<DeltaInt64>, store: &impl StoreDelete) {
+    for prefix in stale_prefixes(days) {
+        store.delete_prefix(0, &prefix);
+    }
+}
+
+// 天数变化时旧一天的全部前缀；pool_key / mint_key 的地址留空即为 "pool:<day>:" / "mint:<day>:"
+fn stale_prefixes(days: &Deltas<DeltaInt64>) -> Vec<String> {
+    days.iter()
+        .filter(|d| d.operation == Operation::Update && d.old_value != d.new_value)
+        .flat_map(|d| [pool_key(d.old_value, ""), mint_key(d.old_value, "")])
+        .collect()
+}
+
+fn pool_key(day: i64, pool: &str) -> String {
+    format!("pool:{}:{}", day, pool)
+}
+
+fn mint_key(day: i64, mint: &str) -> String {
+    format!("mint:{}:{}", day, mint)
+}
+
+// 1970-01-01 起的天数 -> YYYY-MM-DD（proleptic Gregorian，Howard Hinnant 的 civil_from_days）
+fn date(day: i64) -> String {
+    let z = day + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = yoe + era * 400 + if m <= 2 { 1 } else { 0 };
+    format!("{:04}-{:02}-{:02}", y, m, d)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day_delta(operation: Operation, old_value: i64, new_value: i64) -> DeltaInt64 {
+        DeltaInt64 {
+            operation,
+            ordinal: 0,
+            key: "day".to_string(),
+            old_value,
+            new_value,
+        }
+    }
+
+    #[test]
+    fn rollover_prunes_previous_day() {
+        let days = Deltas {
+            deltas: vec![day_delta(Operation::Update, 19_000, 19_001)],
+        };
+        assert_eq!(stale_prefixes(&days), vec!["pool:19000:", "mint:19000:"]);
+    }
+
+    #[test]
+    fn gap_of_several_days_prunes_last_traded_day() {
+        // 上一个有成交的区块在 19000，之后三天没有成交
+        let days = Deltas {
+            deltas: vec![day_delta(Operation::Update, 19_000, 19_003)],
+        };
+        assert_eq!(stale_prefixes(&days), vec!["pool:19000:", "mint:19000:"]);
+    }
+
+    #[test]
+    fn same_day_and_first_block_prune_nothing() {
+        let days = Deltas {
+            deltas: vec![
+                day_delta(Operation::Create, 0, 19_000),
+                day_delta(Operation::Update, 19_000, 19_000),
+            ],
+        };
+        assert!(stale_prefixes(&days).is_empty());
+        assert!(stale_prefixes(&Deltas { deltas: vec![] }).is_empty());
+    }
+
+    #[test]
+    fn day_prefix_does_not_match_other_days() {
+        // "pool:1:" 不会误删 "pool:10:..." 的 key
+        assert!(!pool_key(10, "pool").starts_with(&pool_key(1, "")));
+        assert_eq!(mint_key(19_000, "mint"), "mint:19000:mint");
+    }
+
+    #[test]
+    fn date_from_days() {
+        assert_eq!(date(0), "1970-01-01");
+        assert_eq!(date(-1), "1969-12-31");
+        assert_eq!(date(-719_468), "0000-03-01");
+        // 2000 与 2024 的闰日，1900 不是闰年
+        assert_eq!(date(11_016), "2000-02-29");
+        assert_eq!(date(19_782), "2024-02-29");
+        assert_eq!(date(19_783), "2024-03-01");
+        assert_eq!(date(-25_508), "1900-03-01");
+        assert_eq!(date(-25_509), "1900-02-28");
+    }
+}
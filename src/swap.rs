@@ -0,0 +1,1045 @@
+// Raydium swap 提取：对单笔交易解析内层 Raydium 指令，组装 TradeEvent。
+// 不依赖 substreams 运行时，map_ray_swap、本地回放与其它 crate 共用
+
+use crate::balances::TokenBalances;
+use crate::envelope::{self, block_info};
+use crate::instructions;
+use crate::logs::{self, InvocationTree};
+use crate::pb::io::blockchain::v1::dex::pool::Pool;
+use crate::pb::io::blockchain::v1::dex::trade::{SwapMode, Trade, TradeAmounts, TradeEvent};
+use crate::pb::io::chainstream::v1::common::Block as CBlock;
+use crate::raydium;
+use crate::RAYDIUM;
+use std::collections::HashMap;
+use substreams::store::{StoreGet, StoreGetProto};
+use substreams_solana::pb::sf::solana::r#type::v1::{Block, ConfirmedTransaction};
+
+// 池子注册表查询：substreams 中为 store_pools，离线回放时为内存中的表
+pub trait PoolLookup {
+    fn pool(&self, address: &str) -> Option<Pool>;
+}
+
+impl PoolLookup for StoreGetProto<Pool> {
+    fn pool(&self, address: &str) -> Option<Pool> {
+        self.get_last(address)
+    }
+}
+
+impl PoolLookup for HashMap<String, Pool> {
+    fn pool(&self, address: &str) -> Option<Pool> {
+        self.get(address).cloned()
+    }
+}
+
+// 提取单笔交易时需要的区块级信息；tx_index 为交易在 block.transactions 中的原始位置（含失败交易）
+pub struct BlockContext<'a> {
+    pub block: CBlock,
+    pub tx_index: u32,
+    pub pools: &'a dyn PoolLookup,
+}
+
+impl<'a> BlockContext<'a> {
+    pub fn new(block: &Block, pools: &'a dyn PoolLookup) -> Self {
+        BlockContext {
+            block: block_info(block),
+            tx_index: 0,
+            pools,
+        }
+    }
+}
+
+// 整个区块：按交易顺序依次提取
+pub fn extract_trade_events(block: &Block, pools: &dyn PoolLookup) -> Vec<TradeEvent> {
+    let mut ctx = BlockContext::new(block, pools);
+    let mut events: Vec<TradeEvent> = Vec::new();
+    for (tx_index, tx) in block.transactions.iter().enumerate() {
+        ctx.tx_index = tx_index as u32;
+        events.extend(extract_swaps(tx, &ctx));
+    }
+    events
+}
+
+// 单笔交易中的 Raydium swap（顶层直接调用与 CPI 内层调用）；失败交易与不含 Raydium swap 指令的交易返回空
+pub fn extract_swaps(tx: &ConfirmedTransaction, ctx: &BlockContext) -> Vec<TradeEvent> {
+    let mut events: Vec<TradeEvent> = Vec::new();
+
+    let Some(meta) = tx.meta.as_ref() else {
+        return events;
+    };
+    if meta.err.is_some() {
+        return events;
+    }
+    // 获取 tx 的 message 以解出 program_id
+    let Some(message) = tx.transaction.as_ref().and_then(|t| t.message.as_ref()) else {
+        return events;
+    };
+
+    // 账户表（含 v0 交易通过 ALT 加载的地址），指令中的账户索引按此表解析
+    let account_keys: Vec<String> = tx
+        .resolved_accounts()
+        .iter()
+        .map(|k| bs58::encode(k).into_string())
+        .collect();
+
+    // 顶层与内层指令统一展开：直接调用与经聚合器 CPI 调用的 swap 都要覆盖，
+    // 只保留 Raydium 的 SwapBaseIn / SwapBaseOut
+    let swaps: Vec<instructions::RawInstruction> = instructions::flatten(message, meta)
+        .into_iter()
+        .filter(|ix| {
+            account_keys
+                .get(ix.program_id_index as usize)
+                .map(String::as_str)
+                == Some(RAYDIUM)
+                && raydium::decode_swap(ix.data).is_some()
+        })
+        .collect();
+    if swaps.is_empty() {
+        return events;
+    }
+
+    // 由日志重建调用树，用于把 ray_log 等归属到具体的 Raydium 指令
+    let invocations = InvocationTree::parse(
+        &meta.log_messages,
+        &logs::top_level_programs(message, &account_keys),
+    );
+    let c_tx = envelope::transaction_info(tx, ctx.tx_index);
+    let balances = TokenBalances::new(meta);
+
+    for ix in swaps {
+        // 相关账户（按指令账户索引展开）
+        let accounts: Vec<String> = ix
+            .accounts
+            .iter()
+            .filter_map(|&acc_idx| account_keys.get(acc_idx as usize).cloned())
+            .collect();
+
+        // 金库、用户账户均取自本条指令的账户列表：所有 AMM v4 池子共用同一个 amm_authority，
+        // 按交易整体的余额变动无法区分多跳交易中的各条腿
+        let Some(swap_accounts) = raydium::SwapAccounts::from_accounts(&accounts) else {
+            continue;
+        };
+        let pool_address = swap_accounts.amm.clone();
+        let account_index = |address: &str| {
+            account_keys
+                .iter()
+                .position(|k| k == address)
+                .map(|i| i as u32)
+        };
+        // 只有出现在 pre/post token balances 中的账户才有余额变动，否则数量未知
+        let balance_change = |address: &str| {
+            account_index(address)
+                .filter(|&idx| balances.mint(idx).is_some())
+                .map(|idx| balances.change(idx))
+        };
+        let token_owner = |address: &str, fallback: &str| {
+            account_index(address)
+                .and_then(|idx| balances.owner(idx))
+                .unwrap_or(fallback)
+                .to_string()
+        };
+        let token_mint = |address: &str| {
+            account_index(address)
+                .and_then(|idx| balances.mint(idx))
+                .map(str::to_string)
+        };
+
+        // 已登记的池子以 coin/pc 作为 A/B 的规范方向，金库地址以注册表为准；
+        // 未登记时取本条指令的金库账户及其代币余额中的 mint
+        let registered = ctx
+            .pools
+            .pool(&pool_address)
+            .filter(|p| !p.coin_mint.is_empty() && !p.pc_mint.is_empty());
+        let registry_vault = |vault: fn(&Pool) -> &String| {
+            registered
+                .as_ref()
+                .map(vault)
+                .filter(|v| !v.is_empty())
+                .cloned()
+        };
+        let vault_a_address = registry_vault(|p| &p.coin_vault)
+            .unwrap_or_else(|| swap_accounts.pool_coin_token_account.clone());
+        let vault_b_address = registry_vault(|p| &p.pc_vault)
+            .unwrap_or_else(|| swap_accounts.pool_pc_token_account.clone());
+        let (token_a_mint, token_b_mint) = match registered.as_ref() {
+            Some(p) => (p.coin_mint.clone(), p.pc_mint.clone()),
+            None => (
+                token_mint(&vault_a_address).unwrap_or_default(),
+                token_mint(&vault_b_address).unwrap_or_default(),
+            ),
+        };
+        let vault_a_owner_address = token_owner(&vault_a_address, &swap_accounts.amm_authority);
+        let vault_b_owner_address = token_owner(&vault_b_address, &swap_accounts.amm_authority);
+        let vault_a = balance_change(&vault_a_address).unwrap_or_default();
+        let vault_b = balance_change(&vault_b_address).unwrap_or_default();
+
+        // 用户卖出侧(A)为 user_source，买入侧(B)为 user_destination
+        let user_a_token_account_address = swap_accounts.user_source.clone();
+        let user_b_token_account_address = swap_accounts.user_destination.clone();
+        let user_a_account_owner_address =
+            token_owner(&user_a_token_account_address, &swap_accounts.user_owner);
+        let user_b_account_owner_address =
+            token_owner(&user_b_token_account_address, &swap_accounts.user_owner);
+        let mut user_a = balance_change(&user_a_token_account_address).unwrap_or_default();
+        let mut user_b = balance_change(&user_b_token_account_address).unwrap_or_default();
+
+        // 多跳交易中中间账户先收后付，整笔交易的余额变动不能代表单条腿；
+        // 有 ray_log 时以本条指令实际成交的数量为准
+        if let Some(log) = invocations
+            .get(ix.index, ix.inner_index)
+            .and_then(|inv| inv.ray_log())
+            .and_then(raydium::decode_ray_log)
+        {
+            let (paid, received) = log.user_amounts();
+            user_a.amount = paid.to_string();
+            user_a.delta = Some(paid);
+            user_b.amount = received.to_string();
+            user_b.delta = Some(received);
+        }
+
+        // 判断方向：卖出侧的 mint 是否等于 token_a_mint；源账户无余额时看买入侧
+        let was_original_direction = match token_mint(&user_a_token_account_address) {
+            Some(mint) => mint == token_a_mint,
+            None => token_mint(&user_b_token_account_address)
+                .map(|mint| mint == token_b_mint)
+                .unwrap_or(true),
+        };
+
+        // 指令限额：用户声明的滑点边界、实际成交数量与剩余容忍度
+        let limits = SwapLimits::resolve(
+            ix.data,
+            &invocations,
+            ix.index,
+            ix.inner_index,
+            user_a.delta,
+            user_b.delta,
+        );
+        let amounts = TradeAmounts {
+            user_a_amount: user_a.delta,
+            user_b_amount: user_b.delta,
+            user_a_pre_amount: user_a.pre,
+            user_a_post_amount: user_a.post,
+            user_b_pre_amount: user_b.pre,
+            user_b_post_amount: user_b.post,
+            vault_a_amount: vault_a.delta,
+            vault_b_amount: vault_b.delta,
+            vault_a_pre_amount: vault_a.pre,
+            vault_b_pre_amount: vault_b.pre,
+            vault_a_post_amount: vault_a.post,
+            vault_b_post_amount: vault_b.post,
+            amount_specified: limits.amount_specified,
+            slippage_limit_amount: limits.slippage_limit_amount,
+            realized_amount: limits.realized_amount,
+        };
+
+        let trade = Trade {
+            token_a_address: token_a_mint,
+            token_b_address: token_b_mint,
+            user_a_token_account_address,
+            user_a_account_owner_address,
+            user_b_token_account_address,
+            user_b_account_owner_address,
+            user_a_amount: user_a.amount,
+            user_b_amount: user_b.amount,
+            user_a_pre_amount: user_a.pre_amount,
+            user_a_post_amount: user_a.post_amount,
+            user_b_pre_amount: user_b.pre_amount,
+            user_b_post_amount: user_b.post_amount,
+            was_original_direction,
+            pool_address: pool_address.clone(),
+            vault_a: vault_a_address,
+            vault_b: vault_b_address,
+            vault_a_owner_address,
+            vault_b_owner_address,
+            vault_a_amount: vault_a.amount,
+            vault_b_amount: vault_b.amount,
+            vault_a_pre_amount: vault_a.pre_amount,
+            vault_b_pre_amount: vault_b.pre_amount,
+            vault_a_post_amount: vault_a.post_amount,
+            vault_b_post_amount: vault_b.post_amount,
+            pool_config_address: pool_address,
+            swap_mode: limits.swap_mode as i32,
+            amount_specified: decimal_string(limits.amount_specified),
+            slippage_limit_amount: decimal_string(limits.slippage_limit_amount),
+            realized_amount: decimal_string(limits.realized_amount),
+            unused_tolerance_bps: limits.unused_tolerance_bps,
+            amounts: Some(amounts),
+        };
+
+        events.push(TradeEvent {
+            event_id: instructions::event_id(&c_tx.signature, ix.index, ix.inner_index),
+            ordinal: instructions::ordinal(ctx.tx_index, ix.index, ix.inner_index),
+            instruction: Some(envelope::instruction_info(
+                ix.index,
+                ix.inner_index,
+                "RaydiumSwap",
+                invocations
+                    .get(ix.index, ix.inner_index)
+                    .and_then(|inv| inv.consumed_units)
+                    .unwrap_or_default(),
+            )),
+            block: Some(ctx.block.clone()),
+            transaction: Some(c_tx.clone()),
+            d_app: Some(envelope::dapp(RAYDIUM)),
+            trade: Some(trade),
+            bonding_curve: None,
+        });
+    }
+
+    events
+}
+
+// 指令限额：swap 指令中的数量与滑点边界，以及实际成交数量；非 swap 指令或无法确定时为 None
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SwapLimits {
+    pub swap_mode: SwapMode,
+    pub amount_specified: Option<u64>,
+    pub slippage_limit_amount: Option<u64>,
+    pub realized_amount: Option<u64>,
+    pub unused_tolerance_bps: i64,
+}
+
+impl SwapLimits {
+    // 实际成交数量优先取本条指令自身 ray_log；
+    // 否则 base in 取买入侧(B)收到的数量，base out 取卖出侧(A)付出的数量
+    pub fn resolve(
+        data: &[u8],
+        invocations: &InvocationTree,
+        index: u32,
+        inner_index: Option<u32>,
+        amount_sold: Option<u64>,
+        amount_bought: Option<u64>,
+    ) -> SwapLimits {
+        let mut limits = SwapLimits::default();
+        let Some(swap_ix) = raydium::decode_swap(data) else {
+            return limits;
+        };
+        limits.swap_mode = swap_ix.mode();
+        limits.amount_specified = Some(swap_ix.amount_specified());
+        limits.slippage_limit_amount = Some(swap_ix.slippage_limit());
+
+        let from_ray_log = invocations
+            .get(index, inner_index)
+            .and_then(|inv| inv.ray_log())
+            .and_then(raydium::decode_ray_log)
+            .map(|log| log.realized_amount());
+        let from_balances = match swap_ix {
+            raydium::SwapInstruction::BaseIn { .. } => amount_bought,
+            raydium::SwapInstruction::BaseOut { .. } => amount_sold,
+        };
+        if let Some(realized) = from_ray_log.or(from_balances) {
+            limits.unused_tolerance_bps = swap_ix.unused_tolerance_bps(realized);
+            limits.realized_amount = Some(realized);
+        }
+        limits
+    }
+}
+
+// 旧版字符串字段：未知为空字符串
+fn decimal_string(amount: Option<u64>) -> String {
+    amount.map(|a| a.to_string()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pb::io::blockchain::v1::dex::trade::TradeEvents;
+    use crate::testing::{
+        self, pubkey, signature, swap_base_in_data, BlockBuilder, SwapAccountsSpec,
+        TransactionBuilder,
+    };
+    use prost::Message;
+
+    const DECIMALS: u32 = 6;
+
+    struct Accounts {
+        trader: String,
+        aggregator: String,
+        amm: String,
+        authority: String,
+        coin_vault: String,
+        pc_vault: String,
+        user_coin: String,
+        user_pc: String,
+        coin_mint: String,
+        pc_mint: String,
+    }
+
+    fn accounts() -> Accounts {
+        Accounts {
+            trader: pubkey(10),
+            aggregator: pubkey(11),
+            amm: pubkey(12),
+            authority: pubkey(13),
+            coin_vault: pubkey(14),
+            pc_vault: pubkey(15),
+            user_coin: pubkey(16),
+            user_pc: pubkey(17),
+            coin_mint: pubkey(18),
+            pc_mint: pubkey(19),
+        }
+    }
+
+    // 经聚合器 CPI 调用的 swap base in：卖出 1000 coin，换得 2000 pc（滑点下限 1900）
+    fn swap_tx(a: &Accounts, source: &str, destination: &str) -> TransactionBuilder {
+        let swap_accounts = SwapAccountsSpec {
+            amm: &a.amm,
+            amm_authority: &a.authority,
+            pool_coin_token_account: &a.coin_vault,
+            pool_pc_token_account: &a.pc_vault,
+            user_source: source,
+            user_destination: destination,
+            user_owner: &a.trader,
+        }
+        .to_accounts();
+        TransactionBuilder::new(&signature(1), &a.trader)
+            .instruction(&a.aggregator, &[], vec![])
+            .inner_instruction(0, RAYDIUM, &swap_accounts, swap_base_in_data(1_000, 1_900))
+            .log(&format!("Program {} invoke [1]", a.aggregator))
+            .log("Program log: Instruction: Swap")
+            .log(&format!("Program {} invoke [2]", RAYDIUM))
+            .log(&format!(
+                "Program {} consumed 20000 of 180000 compute units",
+                RAYDIUM
+            ))
+            .log(&format!("Program {} success", RAYDIUM))
+            .log(&format!("Program {} success", a.aggregator))
+    }
+
+    fn vaults(tx: TransactionBuilder, a: &Accounts) -> TransactionBuilder {
+        tx.token_balance(
+            &a.coin_vault,
+            &a.coin_mint,
+            &a.authority,
+            DECIMALS,
+            Some(1_000_000),
+            Some(1_001_000),
+        )
+        .token_balance(
+            &a.pc_vault,
+            &a.pc_mint,
+            &a.authority,
+            DECIMALS,
+            Some(2_000_000),
+            Some(1_998_000),
+        )
+    }
+
+    fn sell_coin(a: &Accounts) -> TransactionBuilder {
+        let tx = swap_tx(a, &a.user_coin, &a.user_pc)
+            .token_balance(
+                &a.user_coin,
+                &a.coin_mint,
+                &a.trader,
+                DECIMALS,
+                Some(5_000),
+                Some(4_000),
+            )
+            .token_balance(
+                &a.user_pc,
+                &a.pc_mint,
+                &a.trader,
+                DECIMALS,
+                Some(0),
+                Some(2_000),
+            );
+        vaults(tx, a)
+    }
+
+    fn extract(block: &Block) -> Vec<TradeEvent> {
+        extract_trade_events(block, &HashMap::new())
+    }
+
+    #[test]
+    fn cpi_swap_is_extracted() {
+        let a = accounts();
+        let block = BlockBuilder::new(300).transaction(sell_coin(&a)).build();
+
+        let events = extract(&block);
+        assert_eq!(events.len(), 1);
+        let event = &events[0];
+        let trade = event.trade.as_ref().unwrap();
+        assert_eq!(trade.pool_address, a.amm);
+        assert_eq!(trade.token_a_address, a.coin_mint);
+        assert_eq!(trade.token_b_address, a.pc_mint);
+        assert!(trade.was_original_direction);
+        assert_eq!(trade.user_a_token_account_address, a.user_coin);
+        assert_eq!(trade.user_b_token_account_address, a.user_pc);
+        assert_eq!(trade.user_a_account_owner_address, a.trader);
+        assert_eq!(trade.user_a_amount, "1000");
+        assert_eq!(trade.user_b_amount, "2000");
+        assert_eq!(trade.vault_a, a.coin_vault);
+        assert_eq!(trade.vault_b, a.pc_vault);
+        assert_eq!(trade.vault_a_amount, "1000");
+        assert_eq!(trade.vault_b_pre_amount, "2000000");
+        assert_eq!(trade.swap_mode, SwapMode::BaseIn as i32);
+        assert_eq!(trade.amount_specified, "1000");
+        assert_eq!(trade.slippage_limit_amount, "1900");
+        assert_eq!(trade.realized_amount, "2000");
+        assert_eq!(trade.unused_tolerance_bps, 500);
+        let amounts = trade.amounts.as_ref().unwrap();
+        assert_eq!(amounts.user_a_amount, Some(1_000));
+        assert_eq!(amounts.user_b_pre_amount, Some(0));
+        assert_eq!(amounts.vault_b_post_amount, Some(1_998_000));
+        assert_eq!(amounts.realized_amount, Some(2_000));
+
+        let instruction = event.instruction.as_ref().unwrap();
+        assert_eq!(
+            (instruction.index, instruction.inner_instruction_index),
+            (0, 0)
+        );
+        assert_eq!(instruction.compute_units_consumed, 20_000);
+        let transaction = event.transaction.as_ref().unwrap();
+        assert_eq!(transaction.signature, signature(1));
+        assert_eq!(transaction.fee_payer, a.trader);
+        assert_eq!(event.event_id, format!("{}-0-0", signature(1)));
+        assert_eq!(event.ordinal, 1);
+        assert_eq!(event.block.as_ref().unwrap().slot, 300);
+    }
+
+    #[test]
+    fn top_level_swap_is_extracted() {
+        let a = accounts();
+        // 用户直接调用 Raydium：swap 为顶层指令，没有内层指令与 "Instruction: Swap" 日志
+        let swap_accounts = SwapAccountsSpec {
+            amm: &a.amm,
+            amm_authority: &a.authority,
+            pool_coin_token_account: &a.coin_vault,
+            pool_pc_token_account: &a.pc_vault,
+            user_source: &a.user_coin,
+            user_destination: &a.user_pc,
+            user_owner: &a.trader,
+        }
+        .to_accounts();
+        let tx = TransactionBuilder::new(&signature(3), &a.trader)
+            .instruction(RAYDIUM, &swap_accounts, swap_base_in_data(1_000, 1_900))
+            .log(&format!("Program {} invoke [1]", RAYDIUM))
+            .log(&format!(
+                "Program {} consumed 30000 of 200000 compute units",
+                RAYDIUM
+            ))
+            .log(&format!("Program {} success", RAYDIUM))
+            .token_balance(
+                &a.user_coin,
+                &a.coin_mint,
+                &a.trader,
+                DECIMALS,
+                Some(5_000),
+                Some(4_000),
+            )
+            .token_balance(
+                &a.user_pc,
+                &a.pc_mint,
+                &a.trader,
+                DECIMALS,
+                Some(0),
+                Some(2_000),
+            );
+        let block = BlockBuilder::new(300).transaction(vaults(tx, &a)).build();
+
+        let events = extract(&block);
+        assert_eq!(events.len(), 1);
+        let event = &events[0];
+        let trade = event.trade.as_ref().unwrap();
+        assert_eq!(trade.pool_address, a.amm);
+        assert_eq!(trade.user_a_token_account_address, a.user_coin);
+        assert_eq!(trade.user_b_amount, "2000");
+        let instruction = event.instruction.as_ref().unwrap();
+        assert_eq!(instruction.index, 0);
+        assert!(!instruction.is_inner_instruction);
+        assert_eq!(instruction.compute_units_consumed, 30_000);
+        assert_eq!(event.event_id, format!("{}-0", signature(3)));
+        assert_eq!(event.ordinal, 0);
+    }
+
+    #[test]
+    fn precompile_before_swap_keeps_compute_units() {
+        let a = accounts();
+        // 顶层指令 0 为 Ed25519 签名校验，不打印调用日志；swap 为顶层指令 1
+        let swap_accounts = SwapAccountsSpec {
+            amm: &a.amm,
+            amm_authority: &a.authority,
+            pool_coin_token_account: &a.coin_vault,
+            pool_pc_token_account: &a.pc_vault,
+            user_source: &a.user_coin,
+            user_destination: &a.user_pc,
+            user_owner: &a.trader,
+        }
+        .to_accounts();
+        let tx = TransactionBuilder::new(&signature(3), &a.trader)
+            .instruction("Ed25519SigVerify111111111111111111111111111", &[], vec![0])
+            .instruction(RAYDIUM, &swap_accounts, swap_base_in_data(1_000, 1_900))
+            .log(&format!("Program {} invoke [1]", RAYDIUM))
+            .log(&format!(
+                "Program {} consumed 30000 of 200000 compute units",
+                RAYDIUM
+            ))
+            .log(&format!("Program {} success", RAYDIUM))
+            .token_balance(
+                &a.user_coin,
+                &a.coin_mint,
+                &a.trader,
+                DECIMALS,
+                Some(5_000),
+                Some(4_000),
+            )
+            .token_balance(
+                &a.user_pc,
+                &a.pc_mint,
+                &a.trader,
+                DECIMALS,
+                Some(0),
+                Some(2_000),
+            );
+        let block = BlockBuilder::new(300).transaction(vaults(tx, &a)).build();
+
+        let events = extract(&block);
+        assert_eq!(events.len(), 1);
+        let instruction = events[0].instruction.as_ref().unwrap();
+        assert_eq!(instruction.index, 1);
+        assert_eq!(instruction.compute_units_consumed, 30_000);
+    }
+
+    #[test]
+    fn alt_loaded_accounts_are_resolved() {
+        let a = accounts();
+        // v0 交易：AMM 与两个金库经 ALT 加载，账户索引排在全部静态账户之后
+        let tx = sell_coin(&a)
+            .lookup_writable(&a.amm)
+            .lookup_writable(&a.coin_vault)
+            .lookup_writable(&a.pc_vault)
+            .lookup_readonly(&a.authority);
+        let block = BlockBuilder::new(300).transaction(tx).build();
+
+        let events = extract(&block);
+        assert_eq!(events.len(), 1);
+        let trade = events[0].trade.as_ref().unwrap();
+        assert_eq!(trade.pool_address, a.amm);
+        assert_eq!(trade.vault_a, a.coin_vault);
+        assert_eq!(trade.vault_b, a.pc_vault);
+        assert_eq!(trade.vault_a_owner_address, a.authority);
+        assert_eq!(trade.user_a_token_account_address, a.user_coin);
+        assert_eq!(trade.user_b_token_account_address, a.user_pc);
+        assert_eq!(trade.user_a_amount, "1000");
+    }
+
+    #[test]
+    fn failed_transaction_is_skipped() {
+        let a = accounts();
+        let block = BlockBuilder::new(300)
+            .transaction(sell_coin(&a).failed())
+            .build();
+        assert!(extract(&block).is_empty());
+    }
+
+    #[test]
+    fn transaction_without_raydium_is_skipped() {
+        let a = accounts();
+        let tx = TransactionBuilder::new(&signature(2), &a.trader)
+            .instruction(&a.aggregator, &[], vec![])
+            .log("Program log: Instruction: Swap");
+        let block = BlockBuilder::new(300).transaction(tx).build();
+        assert!(extract(&block).is_empty());
+    }
+
+    #[test]
+    fn closed_source_account_stays_user_side() {
+        let a = accounts();
+        // 卖出侧账户在交易内被关闭，没有 post token balance
+        let tx = swap_tx(&a, &a.user_coin, &a.user_pc)
+            .token_balance(
+                &a.user_coin,
+                &a.coin_mint,
+                &a.trader,
+                DECIMALS,
+                Some(1_000),
+                None,
+            )
+            .token_balance(
+                &a.user_pc,
+                &a.pc_mint,
+                &a.trader,
+                DECIMALS,
+                Some(0),
+                Some(2_000),
+            );
+        let block = BlockBuilder::new(300).transaction(vaults(tx, &a)).build();
+
+        let events = extract(&block);
+        let trade = events[0].trade.as_ref().unwrap();
+        assert_eq!(trade.user_a_token_account_address, a.user_coin);
+        assert_eq!(trade.user_b_token_account_address, a.user_pc);
+        assert_eq!(trade.user_a_account_owner_address, a.trader);
+        assert_eq!(trade.user_b_amount, "2000");
+        assert!(trade.was_original_direction);
+    }
+
+    #[test]
+    fn closed_source_account_loaded_from_alt() {
+        let a = accounts();
+        // 用户账户经 ALT 加载，卖出侧账户在交易内被关闭
+        let tx = swap_tx(&a, &a.user_coin, &a.user_pc)
+            .lookup_writable(&a.user_coin)
+            .lookup_writable(&a.user_pc)
+            .lookup_readonly(&a.authority)
+            .token_balance(
+                &a.user_coin,
+                &a.coin_mint,
+                &a.trader,
+                DECIMALS,
+                Some(1_000),
+                None,
+            )
+            .token_balance(
+                &a.user_pc,
+                &a.pc_mint,
+                &a.trader,
+                DECIMALS,
+                None,
+                Some(2_000),
+            );
+        let block = BlockBuilder::new(300).transaction(vaults(tx, &a)).build();
+
+        let events = extract(&block);
+        let trade = events[0].trade.as_ref().unwrap();
+        assert_eq!(trade.user_a_token_account_address, a.user_coin);
+        assert_eq!(trade.user_b_token_account_address, a.user_pc);
+        assert_eq!(trade.user_a_amount, "1000");
+        assert_eq!(trade.user_a_post_amount, "0");
+        assert_eq!(trade.user_b_amount, "2000");
+        assert_eq!(trade.vault_a, a.coin_vault);
+        assert!(trade.was_original_direction);
+    }
+
+    #[test]
+    fn user_sides_come_from_instruction_accounts() {
+        let a = accounts();
+        let other_coin = pubkey(22);
+        // 同一交易中另一个 coin 账户变动更大，但不是本条 swap 的 user_source
+        let tx = sell_coin(&a).token_balance(
+            &other_coin,
+            &a.coin_mint,
+            &a.trader,
+            DECIMALS,
+            Some(9_000),
+            Some(0),
+        );
+        let block = BlockBuilder::new(300).transaction(tx).build();
+
+        let events = extract(&block);
+        let trade = events[0].trade.as_ref().unwrap();
+        assert_eq!(trade.user_a_token_account_address, a.user_coin);
+        assert_eq!(trade.user_a_amount, "1000");
+        assert_eq!(trade.user_b_token_account_address, a.user_pc);
+    }
+
+    #[test]
+    fn user_owner_falls_back_to_instruction_account() {
+        let a = accounts();
+        // 源账户没有代币余额条目：数量未知，owner 取指令中的 user_owner
+        let tx = swap_tx(&a, &a.user_coin, &a.user_pc).token_balance(
+            &a.user_pc,
+            &a.pc_mint,
+            &a.trader,
+            DECIMALS,
+            Some(0),
+            Some(2_000),
+        );
+        let block = BlockBuilder::new(300).transaction(vaults(tx, &a)).build();
+
+        let events = extract(&block);
+        let trade = events[0].trade.as_ref().unwrap();
+        assert_eq!(trade.user_a_token_account_address, a.user_coin);
+        assert_eq!(trade.user_a_account_owner_address, a.trader);
+        assert_eq!(trade.amounts.as_ref().unwrap().user_a_amount, None);
+        assert!(trade.was_original_direction);
+    }
+
+    #[test]
+    fn multi_hop_legs_use_their_own_accounts() {
+        let a = accounts();
+        let amm_2 = pubkey(30);
+        let coin_vault_2 = pubkey(31);
+        let pc_vault_2 = pubkey(32);
+        let user_other = pubkey(33);
+        let other_mint = pubkey(34);
+        // coin → pc → other：两个池子共用同一个 amm_authority，中间的 pc 账户先收后付、净变动为 0
+        let leg = |amm: &str, coin_vault: &str, pc_vault: &str, source: &str, dest: &str| {
+            SwapAccountsSpec {
+                amm,
+                amm_authority: &a.authority,
+                pool_coin_token_account: coin_vault,
+                pool_pc_token_account: pc_vault,
+                user_source: source,
+                user_destination: dest,
+                user_owner: &a.trader,
+            }
+            .to_accounts()
+        };
+        let ray_log = |amount_in: u64, out_amount: u64| {
+            testing::ray_log(raydium::RayLog::SwapBaseIn {
+                amount_in,
+                minimum_out: 0,
+                direction: 2,
+                user_source: 0,
+                pool_coin: 0,
+                pool_pc: 0,
+                out_amount,
+            })
+        };
+        let tx = TransactionBuilder::new(&signature(4), &a.trader)
+            .instruction(&a.aggregator, &[], vec![])
+            .inner_instruction(
+                0,
+                RAYDIUM,
+                &leg(&a.amm, &a.coin_vault, &a.pc_vault, &a.user_coin, &a.user_pc),
+                swap_base_in_data(1_000, 1_900),
+            )
+            .inner_instruction(
+                0,
+                RAYDIUM,
+                &leg(&amm_2, &coin_vault_2, &pc_vault_2, &a.user_pc, &user_other),
+                swap_base_in_data(2_000, 400),
+            )
+            .log(&format!("Program {} invoke [1]", a.aggregator))
+            .log(&format!("Program {} invoke [2]", RAYDIUM))
+            .log(&ray_log(1_000, 2_000))
+            .log(&format!("Program {} success", RAYDIUM))
+            .log(&format!("Program {} invoke [2]", RAYDIUM))
+            .log(&ray_log(2_000, 500))
+            .log(&format!("Program {} success", RAYDIUM))
+            .log(&format!("Program {} success", a.aggregator))
+            .token_balance(
+                &a.user_coin,
+                &a.coin_mint,
+                &a.trader,
+                DECIMALS,
+                Some(5_000),
+                Some(4_000),
+            )
+            .token_balance(
+                &a.user_pc,
+                &a.pc_mint,
+                &a.trader,
+                DECIMALS,
+                Some(0),
+                Some(0),
+            )
+            .token_balance(
+                &user_other,
+                &other_mint,
+                &a.trader,
+                DECIMALS,
+                Some(0),
+                Some(500),
+            )
+            .token_balance(
+                &coin_vault_2,
+                &other_mint,
+                &a.authority,
+                DECIMALS,
+                Some(100_000),
+                Some(99_500),
+            )
+            .token_balance(
+                &pc_vault_2,
+                &a.pc_mint,
+                &a.authority,
+                DECIMALS,
+                Some(400_000),
+                Some(402_000),
+            );
+        let block = BlockBuilder::new(300).transaction(vaults(tx, &a)).build();
+
+        let events = extract(&block);
+        assert_eq!(events.len(), 2);
+        let first = events[0].trade.as_ref().unwrap();
+        assert_eq!(first.pool_address, a.amm);
+        assert_eq!(
+            (first.vault_a.as_str(), first.vault_b.as_str()),
+            (a.coin_vault.as_str(), a.pc_vault.as_str())
+        );
+        assert_eq!(first.user_a_token_account_address, a.user_coin);
+        assert_eq!(first.user_b_token_account_address, a.user_pc);
+        assert_eq!(
+            (first.user_a_amount.as_str(), first.user_b_amount.as_str()),
+            ("1000", "2000")
+        );
+        assert!(first.was_original_direction);
+
+        let second = events[1].trade.as_ref().unwrap();
+        assert_eq!(second.pool_address, amm_2);
+        assert_eq!(
+            (second.vault_a.as_str(), second.vault_b.as_str()),
+            (coin_vault_2.as_str(), pc_vault_2.as_str())
+        );
+        assert_eq!(second.vault_b_amount, "2000");
+        assert_eq!(
+            (
+                second.token_a_address.as_str(),
+                second.token_b_address.as_str()
+            ),
+            (other_mint.as_str(), a.pc_mint.as_str())
+        );
+        assert_eq!(second.user_a_token_account_address, a.user_pc);
+        assert_eq!(second.user_b_token_account_address, user_other);
+        assert_eq!(
+            (second.user_a_amount.as_str(), second.user_b_amount.as_str()),
+            ("2000", "500")
+        );
+        assert!(!second.was_original_direction);
+    }
+
+    #[test]
+    fn missing_block_time_defaults_to_zero() {
+        let a = accounts();
+        let block = BlockBuilder::new(300)
+            .block_time(None)
+            .transaction(sell_coin(&a))
+            .build();
+
+        let events = extract(&block);
+        let c_block = events[0].block.as_ref().unwrap();
+        assert_eq!(c_block.timestamp, 0);
+        assert_eq!(c_block.slot, 300);
+    }
+
+    #[test]
+    fn output_is_deterministic_on_ties() {
+        let a = accounts();
+        let second_coin = pubkey(20);
+        let pool_owned_coin = pubkey(21);
+        // 另有一个用户 coin 账户减少相同数量、一个 amm_authority 持有的 coin 账户与金库并列，
+        // 均不在本条指令中，不影响用户侧与金库
+        let tx = sell_coin(&a)
+            .token_balance(
+                &second_coin,
+                &a.coin_mint,
+                &a.trader,
+                DECIMALS,
+                Some(3_000),
+                Some(2_000),
+            )
+            .token_balance(
+                &pool_owned_coin,
+                &a.coin_mint,
+                &a.authority,
+                DECIMALS,
+                Some(10),
+                Some(10),
+            );
+        let block = BlockBuilder::new(300).transaction(tx).build();
+
+        // 每次调用都会新建 map，若依赖哈希顺序，多次运行的结果会不同
+        let encoded: Vec<Vec<u8>> = (0..32)
+            .map(|_| {
+                TradeEvents {
+                    events: extract(&block),
+                }
+                .encode_to_vec()
+            })
+            .collect();
+        assert!(encoded.windows(2).all(|w| w[0] == w[1]));
+
+        let events = extract(&block);
+        let trade = events[0].trade.as_ref().unwrap();
+        assert_eq!(trade.user_a_token_account_address, a.user_coin);
+        assert_eq!(trade.vault_a, a.coin_vault);
+    }
+
+    #[test]
+    fn typed_amounts_distinguish_unknown_from_zero() {
+        let a = accounts();
+        // coin 金库缺少 post 条目（交易内关闭）：post 为已知的 0，字符串字段与类型化字段一致
+        let tx = swap_tx(&a, &a.user_coin, &a.user_pc)
+            .token_balance(
+                &a.user_coin,
+                &a.coin_mint,
+                &a.trader,
+                DECIMALS,
+                Some(5_000),
+                Some(4_000),
+            )
+            .token_balance(
+                &a.user_pc,
+                &a.pc_mint,
+                &a.trader,
+                DECIMALS,
+                Some(0),
+                Some(2_000),
+            )
+            .token_balance(
+                &a.coin_vault,
+                &a.coin_mint,
+                &a.authority,
+                DECIMALS,
+                Some(1_000_000),
+                None,
+            );
+        let block = BlockBuilder::new(300).transaction(tx).build();
+
+        let events = extract(&block);
+        let trade = events[0].trade.as_ref().unwrap();
+        let amounts = trade.amounts.as_ref().unwrap();
+        assert_eq!(trade.vault_a_post_amount, "0");
+        assert_eq!(amounts.vault_a_post_amount, Some(0));
+        assert_eq!(trade.vault_a_amount, "1000000");
+        assert_eq!(amounts.vault_a_amount, Some(1_000_000));
+        assert_eq!(amounts.vault_a_pre_amount, Some(1_000_000));
+        assert_eq!(trade.vault_post_amount(&a.coin_mint), Some(0));
+        // pc 金库没有代币余额条目：数量全部未知，字符串字段为空
+        assert_eq!(trade.vault_b_amount, "");
+        assert_eq!(trade.vault_b_pre_amount, "");
+        assert_eq!(trade.vault_b_post_amount, "");
+        assert_eq!(amounts.vault_b_pre_amount, None);
+        assert_eq!(amounts.user_b_pre_amount, Some(0));
+    }
+
+    #[test]
+    fn registered_pool_sets_direction() {
+        let a = accounts();
+        // 卖出 pc 买入 coin：未登记时由指令中金库账户的 mint 确定 A/B，登记后以注册表为准
+        let tx = swap_tx(&a, &a.user_pc, &a.user_coin)
+            .token_balance(
+                &a.user_pc,
+                &a.pc_mint,
+                &a.trader,
+                DECIMALS,
+                Some(2_000),
+                Some(0),
+            )
+            .token_balance(
+                &a.user_coin,
+                &a.coin_mint,
+                &a.trader,
+                DECIMALS,
+                Some(0),
+                Some(1_000),
+            );
+        let block = BlockBuilder::new(300).transaction(vaults(tx, &a)).build();
+
+        let unregistered = extract(&block);
+        let trade = unregistered[0].trade.as_ref().unwrap();
+        assert_eq!(trade.token_a_address, a.coin_mint);
+        assert!(!trade.was_original_direction);
+
+        let mut pools: HashMap<String, Pool> = HashMap::new();
+        pools.insert(
+            a.amm.clone(),
+            Pool {
+                address: a.amm.clone(),
+                coin_mint: a.coin_mint.clone(),
+                pc_mint: a.pc_mint.clone(),
+                coin_vault: a.coin_vault.clone(),
+                pc_vault: a.pc_vault.clone(),
+                ..Default::default()
+            },
+        );
+        let events = extract_trade_events(&block, &pools);
+        let trade = events[0].trade.as_ref().unwrap();
+        assert_eq!(trade.token_a_address, a.coin_mint);
+        assert_eq!(trade.token_b_address, a.pc_mint);
+        assert!(!trade.was_original_direction);
+        assert_eq!(trade.user_a_token_account_address, a.user_pc);
+        assert_eq!(trade.user_a_amount, "2000");
+    }
+}
@@ -0,0 +1,448 @@
+// 测试用的合成区块构造器：按地址描述账户、指令、日志与余额，构造时再统一分配账户索引。
+// 账户顺序为：静态账户（按首次出现）、ALT 加载的可写账户、ALT 加载的只读账户
+
+use crate::raydium;
+use substreams_solana::pb::sf::solana::r#type::v1::{
+    Block, BlockHeight, CompiledInstruction, ConfirmedTransaction, InnerInstruction,
+    InnerInstructions, Message, MessageHeader, TokenBalance, Transaction, TransactionError,
+    TransactionStatusMeta, UiTokenAmount, UnixTimestamp,
+};
+
+// 由种子生成确定的 32 字节地址（base58）
+pub fn pubkey(seed: u8) -> String {
+    bs58::encode([seed; 32]).into_string()
+}
+
+// 由种子生成确定的 64 字节签名（base58）
+pub fn signature(seed: u8) -> String {
+    bs58::encode([seed; 64]).into_string()
+}
+
+pub fn swap_base_in_data(amount_in: u64, minimum_amount_out: u64) -> Vec<u8> {
+    swap_data(raydium::SWAP_BASE_IN, amount_in, minimum_amount_out)
+}
+
+pub fn swap_base_out_data(max_amount_in: u64, amount_out: u64) -> Vec<u8> {
+    swap_data(raydium::SWAP_BASE_OUT, max_amount_in, amount_out)
+}
+
+fn swap_data(tag: u8, first: u64, second: u64) -> Vec<u8> {
+    let mut data = vec![tag];
+    data.extend_from_slice(&first.to_le_bytes());
+    data.extend_from_slice(&second.to_le_bytes());
+    data
+}
+
+// "Program log: ray_log: <base64>" 日志行，与 raydium::decode_ray_log 互逆
+pub fn ray_log(log: raydium::RayLog) -> String {
+    use base64::Engine;
+
+    let (tag, fields) = match log {
+        raydium::RayLog::SwapBaseIn {
+            amount_in,
+            minimum_out,
+            direction,
+            user_source,
+            pool_coin,
+            pool_pc,
+            out_amount,
+        } => (
+            raydium::LOG_SWAP_BASE_IN,
+            [
+                amount_in,
+                minimum_out,
+                direction,
+                user_source,
+                pool_coin,
+                pool_pc,
+                out_amount,
+            ],
+        ),
+        raydium::RayLog::SwapBaseOut {
+            max_in,
+            amount_out,
+            direction,
+            user_source,
+            pool_coin,
+            pool_pc,
+            deduct_in,
+        } => (
+            raydium::LOG_SWAP_BASE_OUT,
+            [
+                max_in,
+                amount_out,
+                direction,
+                user_source,
+                pool_coin,
+                pool_pc,
+                deduct_in,
+            ],
+        ),
+    };
+    let mut bytes = vec![tag];
+    for field in fields {
+        bytes.extend_from_slice(&field.to_le_bytes());
+    }
+    format!(
+        "Program log: ray_log: {}",
+        base64::engine::general_purpose::STANDARD.encode(bytes)
+    )
+}
+
+// Raydium swap 的 17 账户布局（不含 amm_target_orders）
+pub struct SwapAccountsSpec<'a> {
+    pub amm: &'a str,
+    pub amm_authority: &'a str,
+    pub pool_coin_token_account: &'a str,
+    pub pool_pc_token_account: &'a str,
+    pub user_source: &'a str,
+    pub user_destination: &'a str,
+    pub user_owner: &'a str,
+}
+
+impl SwapAccountsSpec<'_> {
+    pub fn to_accounts(&self) -> Vec<String> {
+        vec![
+            pubkey(200), // token_program
+            self.amm.to_string(),
+            self.amm_authority.to_string(),
+            pubkey(201), // amm_open_orders
+            self.pool_coin_token_account.to_string(),
+            self.pool_pc_token_account.to_string(),
+            pubkey(202), // serum_program
+            pubkey(203), // serum_market
+            pubkey(204), // serum_bids
+            pubkey(205), // serum_asks
+            pubkey(206), // serum_event_queue
+            pubkey(207), // serum_coin_vault
+            pubkey(208), // serum_pc_vault
+            pubkey(209), // serum_vault_signer
+            self.user_source.to_string(),
+            self.user_destination.to_string(),
+            self.user_owner.to_string(),
+        ]
+    }
+}
+
+pub struct BlockBuilder {
+    block: Block,
+}
+
+impl BlockBuilder {
+    pub fn new(slot: u64) -> Self {
+        BlockBuilder {
+            block: Block {
+                slot,
+                parent_slot: slot.saturating_sub(1),
+                blockhash: pubkey(250),
+                previous_blockhash: pubkey(251),
+                block_time: Some(UnixTimestamp {
+                    timestamp: 1_700_000_000,
+                }),
+                block_height: Some(BlockHeight { block_height: slot }),
+                ..Default::default()
+            },
+        }
+    }
+
+    // None 对应区块缺失 block_time
+    pub fn block_time(mut self, timestamp: Option<i64>) -> Self {
+        self.block.block_time = timestamp.map(|timestamp| UnixTimestamp { timestamp });
+        self
+    }
+
+    pub fn block_height(mut self, height: Option<u64>) -> Self {
+        self.block.block_height = height.map(|block_height| BlockHeight { block_height });
+        self
+    }
+
+    pub fn blockhash(mut self, hash: &str) -> Self {
+        self.block.blockhash = hash.to_string();
+        self
+    }
+
+    pub fn transaction(mut self, tx: TransactionBuilder) -> Self {
+        self.block.transactions.push(tx.build());
+        self
+    }
+
+    pub fn build(self) -> Block {
+        self.block
+    }
+}
+
+struct PendingInstruction {
+    program: String,
+    accounts: Vec<String>,
+    data: Vec<u8>,
+}
+
+struct PendingTokenBalance {
+    account: String,
+    mint: String,
+    owner: String,
+    decimals: u32,
+    pre: Option<u64>,
+    post: Option<u64>,
+}
+
+pub struct TransactionBuilder {
+    signature: String,
+    static_keys: Vec<String>,
+    loaded_writable: Vec<String>,
+    loaded_readonly: Vec<String>,
+    instructions: Vec<PendingInstruction>,
+    // (顶层指令序号, 内层指令)
+    inner_instructions: Vec<(u32, PendingInstruction)>,
+    logs: Vec<String>,
+    token_balances: Vec<PendingTokenBalance>,
+    lamports: Vec<(String, u64, u64)>,
+    fee: u64,
+    compute_units_consumed: Option<u64>,
+    // bincode 编码的 TransactionError
+    err: Option<Vec<u8>>,
+}
+
+impl TransactionBuilder {
+    // signer 即 fee payer，固定为账户 0
+    pub fn new(signature: &str, signer: &str) -> Self {
+        TransactionBuilder {
+            signature: signature.to_string(),
+            static_keys: vec![signer.to_string()],
+            loaded_writable: Vec::new(),
+            loaded_readonly: Vec::new(),
+            instructions: Vec::new(),
+            inner_instructions: Vec::new(),
+            logs: Vec::new(),
+            token_balances: Vec::new(),
+            lamports: Vec::new(),
+            fee: 5000,
+            compute_units_consumed: None,
+            err: None,
+        }
+    }
+
+    pub fn instruction(mut self, program: &str, accounts: &[String], data: Vec<u8>) -> Self {
+        self.instructions.push(pending(program, accounts, data));
+        self
+    }
+
+    // index 为所属顶层指令的序号；同一 index 下按调用顺序追加
+    pub fn inner_instruction(
+        mut self,
+        index: u32,
+        program: &str,
+        accounts: &[String],
+        data: Vec<u8>,
+    ) -> Self {
+        self.inner_instructions
+            .push((index, pending(program, accounts, data)));
+        self
+    }
+
+    pub fn log(mut self, line: &str) -> Self {
+        self.logs.push(line.to_string());
+        self
+    }
+
+    // pre / post 为 None 时不写入对应的 token balance 条目
+    pub fn token_balance(
+        mut self,
+        account: &str,
+        mint: &str,
+        owner: &str,
+        decimals: u32,
+        pre: Option<u64>,
+        post: Option<u64>,
+    ) -> Self {
+        self.token_balances.push(PendingTokenBalance {
+            account: account.to_string(),
+            mint: mint.to_string(),
+            owner: owner.to_string(),
+            decimals,
+            pre,
+            post,
+        });
+        self
+    }
+
+    // 未指定的账户 lamports 余额为 0
+    pub fn lamports(mut self, account: &str, pre: u64, post: u64) -> Self {
+        self.lamports.push((account.to_string(), pre, post));
+        self
+    }
+
+    // 通过 ALT 加载的账户，排在所有静态账户之后
+    pub fn lookup_writable(mut self, account: &str) -> Self {
+        self.loaded_writable.push(account.to_string());
+        self
+    }
+
+    pub fn lookup_readonly(mut self, account: &str) -> Self {
+        self.loaded_readonly.push(account.to_string());
+        self
+    }
+
+    pub fn fee(mut self, fee: u64) -> Self {
+        self.fee = fee;
+        self
+    }
+
+    pub fn compute_units_consumed(mut self, units: u64) -> Self {
+        self.compute_units_consumed = Some(units);
+        self
+    }
+
+    // 交易级错误 AccountLoadedTwice，不含指令序号
+    pub fn failed(self) -> Self {
+        self.error(vec![1, 0, 0, 0])
+    }
+
+    pub fn error(mut self, err: Vec<u8>) -> Self {
+        self.err = Some(err);
+        self
+    }
+
+    pub fn build(mut self) -> ConfirmedTransaction {
+        self.collect_static_keys();
+        let keys: Vec<String> = self
+            .static_keys
+            .iter()
+            .chain(&self.loaded_writable)
+            .chain(&self.loaded_readonly)
+            .cloned()
+            .collect();
+        let index_of =
+            |address: &str| -> u32 { keys.iter().position(|k| k == address).unwrap() as u32 };
+        let account_indexes = |accounts: &[String]| -> Vec<u8> {
+            accounts.iter().map(|a| index_of(a) as u8).collect()
+        };
+
+        let instructions: Vec<CompiledInstruction> = self
+            .instructions
+            .iter()
+            .map(|ix| CompiledInstruction {
+                program_id_index: index_of(&ix.program),
+                accounts: account_indexes(&ix.accounts),
+                data: ix.data.clone(),
+            })
+            .collect();
+
+        let mut inner_instructions: Vec<InnerInstructions> = Vec::new();
+        for (index, ix) in &self.inner_instructions {
+            let compiled = InnerInstruction {
+                program_id_index: index_of(&ix.program),
+                accounts: account_indexes(&ix.accounts),
+                data: ix.data.clone(),
+                stack_height: Some(2),
+            };
+            match inner_instructions
+                .iter_mut()
+                .find(|inner| inner.index == *index)
+            {
+                Some(inner) => inner.instructions.push(compiled),
+                None => inner_instructions.push(InnerInstructions {
+                    index: *index,
+                    instructions: vec![compiled],
+                }),
+            }
+        }
+        inner_instructions.sort_by_key(|inner| inner.index);
+
+        let token_balance = |b: &PendingTokenBalance, amount: u64| TokenBalance {
+            account_index: index_of(&b.account),
+            mint: b.mint.clone(),
+            ui_token_amount: Some(UiTokenAmount {
+                ui_amount: amount as f64 / 10f64.powi(b.decimals as i32),
+                decimals: b.decimals,
+                amount: amount.to_string(),
+                ui_amount_string: String::new(),
+            }),
+            owner: b.owner.clone(),
+            program_id: "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA".to_string(),
+        };
+        let pre_token_balances = self
+            .token_balances
+            .iter()
+            .filter_map(|b| b.pre.map(|amount| token_balance(b, amount)))
+            .collect();
+        let post_token_balances = self
+            .token_balances
+            .iter()
+            .filter_map(|b| b.post.map(|amount| token_balance(b, amount)))
+            .collect();
+
+        let mut pre_balances = vec![0u64; keys.len()];
+        let mut post_balances = vec![0u64; keys.len()];
+        for (account, pre, post) in &self.lamports {
+            let idx = index_of(account) as usize;
+            pre_balances[idx] = *pre;
+            post_balances[idx] = *post;
+        }
+
+        let decode = |k: &String| bs58::decode(k).into_vec().unwrap();
+        let versioned = !self.loaded_writable.is_empty() || !self.loaded_readonly.is_empty();
+        ConfirmedTransaction {
+            transaction: Some(Transaction {
+                signatures: vec![decode(&self.signature)],
+                message: Some(Message {
+                    header: Some(MessageHeader {
+                        num_required_signatures: 1,
+                        num_readonly_signed_accounts: 0,
+                        num_readonly_unsigned_accounts: 0,
+                    }),
+                    account_keys: self.static_keys.iter().map(decode).collect(),
+                    recent_blockhash: vec![0; 32],
+                    instructions,
+                    versioned,
+                    address_table_lookups: Vec::new(),
+                }),
+            }),
+            meta: Some(TransactionStatusMeta {
+                err: self.err.map(|err| TransactionError { err }),
+                fee: self.fee,
+                pre_balances,
+                post_balances,
+                inner_instructions,
+                log_messages: self.logs,
+                pre_token_balances,
+                post_token_balances,
+                loaded_writable_addresses: self.loaded_writable.iter().map(decode).collect(),
+                loaded_readonly_addresses: self.loaded_readonly.iter().map(decode).collect(),
+                compute_units_consumed: self.compute_units_consumed,
+                ..Default::default()
+            }),
+        }
+    }
+
+    // 静态账户：除 ALT 加载外，按首次出现顺序登记
+    fn collect_static_keys(&mut self) {
+        let mut mentioned: Vec<String> = Vec::new();
+        for ix in self
+            .instructions
+            .iter()
+            .chain(self.inner_instructions.iter().map(|(_, ix)| ix))
+        {
+            mentioned.push(ix.program.clone());
+            mentioned.extend(ix.accounts.iter().cloned());
+        }
+        mentioned.extend(self.token_balances.iter().map(|b| b.account.clone()));
+        mentioned.extend(self.lamports.iter().map(|(a, _, _)| a.clone()));
+
+        for address in mentioned {
+            let loaded =
+                self.loaded_writable.contains(&address) || self.loaded_readonly.contains(&address);
+            if !loaded && !self.static_keys.contains(&address) {
+                self.static_keys.push(address);
+            }
+        }
+    }
+}
+
+fn pending(program: &str, accounts: &[String], data: Vec<u8>) -> PendingInstruction {
+    PendingInstruction {
+        program: program.to_string(),
+        accounts: accounts.to_vec(),
+        data,
+    }
+}
@@ -0,0 +1,94 @@
+// TradeEvent / Trade 的便捷访问：执行顺序、卖出/买入侧的 mint 与数量、按 mint 取金库余额
+
+use crate::pb::io::blockchain::v1::dex::trade::{Trade, TradeAmounts, TradeEvent};
+
+impl TradeEvent {
+    pub fn tx_index(&self) -> u32 {
+        self.transaction
+            .as_ref()
+            .map(|t| t.index)
+            .unwrap_or_default()
+    }
+
+    // 区块内执行顺序：(交易序号, 顶层指令序号, 内层指令序号)
+    pub fn execution_order(&self) -> (u32, u32, u32) {
+        let ix = self.instruction.as_ref();
+        (
+            self.tx_index(),
+            ix.map(|i| i.index).unwrap_or_default(),
+            ix.map(|i| i.inner_instruction_index).unwrap_or_default(),
+        )
+    }
+}
+
+impl Trade {
+    // 用户卖出侧（A）的 mint
+    pub fn sold_mint(&self) -> &str {
+        if self.was_original_direction {
+            &self.token_a_address
+        } else {
+            &self.token_b_address
+        }
+    }
+
+    // 用户买入侧（B）的 mint
+    pub fn bought_mint(&self) -> &str {
+        if self.was_original_direction {
+            &self.token_b_address
+        } else {
+            &self.token_a_address
+        }
+    }
+
+    // 有类型化数量（amounts）时以其为准，未知即为 None；旧数据回退解析字符串字段
+    fn amount(&self, typed: fn(&TradeAmounts) -> Option<u64>, legacy: &str) -> Option<u128> {
+        match self.amounts.as_ref() {
+            Some(amounts) => typed(amounts).map(u128::from),
+            None => legacy.parse().ok(),
+        }
+    }
+
+    pub fn amount_sold(&self) -> Option<u128> {
+        self.amount(|a| a.user_a_amount, &self.user_a_amount)
+    }
+
+    pub fn amount_bought(&self) -> Option<u128> {
+        self.amount(|a| a.user_b_amount, &self.user_b_amount)
+    }
+
+    // 该 mint 在本次交易中的成交数量（卖出或买入侧）
+    pub fn amount_of(&self, mint: &str) -> Option<u128> {
+        if mint == self.sold_mint() {
+            self.amount_sold()
+        } else if mint == self.bought_mint() {
+            self.amount_bought()
+        } else {
+            None
+        }
+    }
+
+    // 交易者：卖出侧代币账户的 owner
+    pub fn trader(&self) -> &str {
+        &self.user_a_account_owner_address
+    }
+
+    pub fn vault_pre_amount(&self, mint: &str) -> Option<u128> {
+        if mint == self.token_a_address {
+            self.amount(|a| a.vault_a_pre_amount, &self.vault_a_pre_amount)
+        } else if mint == self.token_b_address {
+            self.amount(|a| a.vault_b_pre_amount, &self.vault_b_pre_amount)
+        } else {
+            None
+        }
+    }
+
+    pub fn vault_post_amount(&self, mint: &str) -> Option<u128> {
+        if mint == self.token_a_address {
+            self.amount(|a| a.vault_a_post_amount, &self.vault_a_post_amount)
+        } else if mint == self.token_b_address {
+            self.amount(|a| a.vault_b_post_amount, &self.vault_b_post_amount)
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,218 @@
+// 池子 TVL 与深度：每次储备变化后按恒定乘积曲线计算，存入 store_pool_tvl，
+// map_pool_tvl 只输出本区块 TVL / 深度实际发生变化的池子
+
+use std::collections::BTreeMap;
+
+use crate::math::sqrt;
+use crate::pb::io::blockchain::v1::dex::pool::{
+    Pool, PoolDepth, PoolLiquidities, PoolLiquidity, PoolReserves, PoolReservesUpdates,
+};
+use crate::pb::io::blockchain::v1::dex::trade::TradeEvents;
+use crate::price;
+use crate::raydium::{SWAP_FEE_DENOMINATOR, SWAP_FEE_NUMERATOR};
+use crate::reserves::reserve_updates;
+use substreams::scalar::BigDecimal;
+use substreams::store::{
+    DeltaProto, Deltas, StoreGet, StoreGetProto, StoreNew, StoreSet, StoreSetProto,
+};
+
+// 价格变动 1% / 2% / 5%
+const DEPTH_BPS: &[u32] = &[100, 200, 500];
+
+#[substreams::handlers::store]
+fn store_pool_tvl(
+    trades: TradeEvents,
+    liquidity: PoolReservesUpdates,
+    pools: StoreGetProto<Pool>,
+    reserves: StoreGetProto<PoolReserves>,
+    store: StoreSetProto<PoolLiquidity>,
+) {
+    for update in reserve_updates(&trades, &liquidity, &pools) {
+        let ordinal = update.reserves.ordinal;
+        let sol_usd = price::sol_usd_at(&reserves, ordinal);
+        if let Some(liquidity) = pool_liquidity(&update.pool, &update.reserves, sol_usd.as_ref()) {
+            store.set(ordinal, &update.pool.address, &liquidity);
+        }
+    }
+}
+
+#[substreams::handlers::map]
+fn map_pool_tvl(
+    updates: Deltas<DeltaProto<PoolLiquidity>>,
+) -> Result<PoolLiquidities, substreams::errors::Error> {
+    // 每个池子取区块开始前的值与区块结束后的值比较
+    let mut changes: BTreeMap<&str, (&PoolLiquidity, &PoolLiquidity)> = BTreeMap::new();
+    for delta in updates.iter() {
+        changes
+            .entry(delta.key.as_str())
+            .and_modify(|(_, after)| *after = &delta.new_value)
+            .or_insert((&delta.old_value, &delta.new_value));
+    }
+
+    let pools = changes
+        .into_values()
+        .filter(|(before, after)| {
+            before.tvl_quote != after.tvl_quote
+                || before.tvl_usd != after.tvl_usd
+                || before.depths != after.depths
+        })
+        .map(|(_, after)| after.clone())
+        .collect();
+
+    Ok(PoolLiquidities { pools })
+}
+
+fn pool_liquidity(
+    pool: &Pool,
+    reserves: &PoolReserves,
+    sol_usd: Option<&BigDecimal>,
+) -> Option<PoolLiquidity> {
+    let coin = price::to_decimal(reserves.coin_reserve.parse().ok()?, pool.coin_decimals);
+    let pc = price::to_decimal(reserves.pc_reserve.parse().ok()?, pool.pc_decimals);
+    let spot = BigDecimal::try_from(reserves.price.as_str()).ok()?;
+    if spot == BigDecimal::zero() {
+        return None;
+    }
+
+    // 恒定乘积池两侧价值相等
+    let tvl_quote = pc.clone() + coin.clone() * spot.clone();
+    let tvl_usd = if let Some((unit, _)) = price::usd_per_unit(&pool.pc_mint, sol_usd) {
+        Some(tvl_quote.clone() * unit)
+    } else {
+        price::usd_per_unit(&pool.coin_mint, sol_usd)
+            .map(|(unit, _)| tvl_quote.clone() / spot.clone() * unit)
+    };
+
+    Some(PoolLiquidity {
+        pool_address: pool.address.clone(),
+        coin_mint: pool.coin_mint.clone(),
+        pc_mint: pool.pc_mint.clone(),
+        coin_reserve: coin.to_string(),
+        pc_reserve: pc.to_string(),
+        price: reserves.price.clone(),
+        tvl_quote: tvl_quote.with_prec(price::PRICE_PRECISION).to_string(),
+        tvl_usd: tvl_usd
+            .map(|v| v.with_prec(price::PRICE_PRECISION).to_string())
+            .unwrap_or_default(),
+        depths: DEPTH_BPS
+            .iter()
+            .map(|&bps| depth(&coin, &pc, &spot, bps))
+            .collect(),
+        slot: reserves.slot,
+        timestamp: reserves.timestamp,
+        ordinal: reserves.ordinal,
+    })
+}
+
+// x * y = k，价格 p = y / x。价格升至 p(1+d) 时 y' = y√(1+d)，需投入 y(√(1+d) - 1) 的 pc；
+// 价格降至 p(1-d) 时 x' = x / √(1-d)，需卖出 x(1/√(1-d) - 1) 的 coin。投入量按手续费放大
+fn depth(x: &BigDecimal, y: &BigDecimal, p: &BigDecimal, bps: u32) -> PoolDepth {
+    let one = BigDecimal::one();
+    let d = BigDecimal::from(bps) / BigDecimal::from(10_000u32);
+    let fee = one.clone()
+        - BigDecimal::from(SWAP_FEE_NUMERATOR as u64)
+            / BigDecimal::from(SWAP_FEE_DENOMINATOR as u64);
+    let up = sqrt(&(one.clone() + d.clone())).unwrap_or_default();
+    let down = sqrt(&(one.clone() - d)).unwrap_or_default();
+    let buy_quote = y.clone() * (up - one.clone()) / fee.clone();
+    let sell_base = x.clone() * (one.clone() / down - one) / fee;
+    PoolDepth {
+        bps,
+        buy_quote: buy_quote.with_prec(price::PRICE_PRECISION).to_string(),
+        sell_quote: (sell_base.clone() * p.clone())
+            .with_prec(price::PRICE_PRECISION)
+            .to_string(),
+        sell_base: sell_base.with_prec(price::PRICE_PRECISION).to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decimal(s: &str) -> BigDecimal {
+        BigDecimal::try_from(s).unwrap()
+    }
+
+    fn assert_close(actual: &str, expected: &str) {
+        let diff = (decimal(actual) - decimal(expected)).absolute();
+        assert!(
+            diff < decimal("0.000000000000000001"),
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn constant_product_depth() {
+        // 1000 coin / 2000 pc，现价 2
+        let (x, y, p) = (
+            BigDecimal::from(1_000),
+            BigDecimal::from(2_000),
+            BigDecimal::from(2),
+        );
+        let expected = [
+            (
+                100,
+                "10.00012453551684615925",
+                "5.050441362618622044485",
+                "10.10088272523724408897",
+            ),
+            (
+                200,
+                "19.95086483450184528589",
+                "10.17798952602581367826",
+                "20.35597905205162735652",
+            ),
+            (
+                500,
+                "49.51393803701220716211",
+                "26.04346073699658692399",
+                "52.08692147399317384797",
+            ),
+        ];
+        for (bps, buy_quote, sell_base, sell_quote) in expected {
+            let depth = depth(&x, &y, &p, bps);
+            assert_eq!(depth.bps, bps);
+            assert_close(&depth.buy_quote, buy_quote);
+            assert_close(&depth.sell_base, sell_base);
+            assert_close(&depth.sell_quote, sell_quote);
+        }
+    }
+
+    #[test]
+    fn buy_depth_moves_price_by_bps() {
+        // 扣除手续费后投入池子，新价格 y'^2 / k 应为现价的 1.02 倍
+        let (x, y) = (BigDecimal::from(1_000), BigDecimal::from(2_000));
+        let depth = depth(&x, &y, &BigDecimal::from(2), 200);
+        let fee = decimal("0.9975");
+        let y1 = y.clone() + decimal(&depth.buy_quote) * fee;
+        let price = y1.clone() * y1 / (x * y);
+        assert_close(&price.to_string(), "2.04");
+    }
+
+    #[test]
+    fn tvl_is_valued_in_quote_and_usd() {
+        let pool = Pool {
+            address: "pool".to_string(),
+            coin_mint: price::WSOL_MINT.to_string(),
+            pc_mint: price::USDC_MINT.to_string(),
+            coin_decimals: 9,
+            pc_decimals: 6,
+            ..Default::default()
+        };
+        let reserves = PoolReserves {
+            coin_reserve: "10000000000".to_string(),
+            pc_reserve: "1500000000".to_string(),
+            price: "150".to_string(),
+            ..Default::default()
+        };
+        let liquidity = pool_liquidity(&pool, &reserves, None).unwrap();
+        assert_close(&liquidity.coin_reserve, "10");
+        assert_close(&liquidity.pc_reserve, "1500");
+        assert_close(&liquidity.tvl_quote, "3000");
+        assert_close(&liquidity.tvl_usd, "3000");
+        assert_eq!(liquidity.depths.len(), DEPTH_BPS.len());
+    }
+}
@@ -0,0 +1,182 @@
+// 交易失败原因解析：meta.err（bincode 编码的 TransactionError）与 "Program ... failed: ..." 日志
+
+use regex::Regex;
+use std::sync::OnceLock;
+use substreams_solana::pb::sf::solana::r#type::v1::TransactionError;
+
+// TransactionError::InstructionError(u8, InstructionError) 的枚举序号
+const TRANSACTION_ERROR_INSTRUCTION_ERROR: u32 = 8;
+// InstructionError::Custom(u32) 的枚举序号
+const INSTRUCTION_ERROR_CUSTOM: u32 = 25;
+
+// InstructionError 枚举名（按 solana-program 中的声明顺序，Custom 之前的部分）
+const INSTRUCTION_ERRORS: &[&str] = &[
+    "GenericError",
+    "InvalidArgument",
+    "InvalidInstructionData",
+    "InvalidAccountData",
+    "AccountDataTooSmall",
+    "InsufficientFunds",
+    "IncorrectProgramId",
+    "MissingRequiredSignature",
+    "AccountAlreadyInitialized",
+    "UninitializedAccount",
+    "UnbalancedInstruction",
+    "ModifiedProgramId",
+    "ExternalAccountLamportSpend",
+    "ExternalAccountDataModified",
+    "ReadonlyLamportChange",
+    "ReadonlyDataModified",
+    "DuplicateAccountIndex",
+    "ExecutableModified",
+    "RentEpochModified",
+    "NotEnoughAccountKeys",
+    "AccountDataSizeChanged",
+    "AccountNotExecutable",
+    "AccountBorrowFailed",
+    "AccountBorrowOutstanding",
+    "DuplicateAccountOutOfSync",
+];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InstructionFailure {
+    pub instruction_index: u32,
+    pub custom_code: Option<u32>,
+    pub kind: String,
+}
+
+// 解析 meta.err；仅处理 InstructionError，其它交易级错误返回 None
+pub fn decode_instruction_error(err: &TransactionError) -> Option<InstructionFailure> {
+    let bytes = &err.err;
+    let read_u32 = |at: usize| -> Option<u32> {
+        Some(u32::from_le_bytes(bytes.get(at..at + 4)?.try_into().ok()?))
+    };
+
+    if read_u32(0)? != TRANSACTION_ERROR_INSTRUCTION_ERROR {
+        return None;
+    }
+    let instruction_index = *bytes.get(4)? as u32;
+    let variant = read_u32(5)?;
+    if variant == INSTRUCTION_ERROR_CUSTOM {
+        return Some(InstructionFailure {
+            instruction_index,
+            custom_code: Some(read_u32(9)?),
+            kind: "Custom".to_string(),
+        });
+    }
+
+    let kind = INSTRUCTION_ERRORS
+        .get(variant as usize)
+        .map(|s| s.to_string())
+        .unwrap_or_else(|| format!("InstructionError({})", variant));
+    Some(InstructionFailure {
+        instruction_index,
+        custom_code: None,
+        kind,
+    })
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FailedLog {
+    pub program: String,
+    pub custom_code: Option<u32>,
+    pub message: String,
+}
+
+// 找到第一条 "Program X failed: ..." 日志，即最先抛出错误的（最内层）程序
+pub fn first_failed_log(logs: &[String]) -> Option<FailedLog> {
+    static FAILED: OnceLock<Regex> = OnceLock::new();
+    static CUSTOM: OnceLock<Regex> = OnceLock::new();
+    let failed = FAILED.get_or_init(|| Regex::new(r"^Program (\w+) failed: (.*)$").unwrap());
+    let custom =
+        CUSTOM.get_or_init(|| Regex::new(r"custom program error: 0x([0-9a-fA-F]+)").unwrap());
+
+    logs.iter().find_map(|log| {
+        let caps = failed.captures(log)?;
+        let custom_code = custom
+            .captures(&caps[2])
+            .and_then(|c| u32::from_str_radix(&c[1], 16).ok());
+        Some(FailedLog {
+            program: caps[1].to_string(),
+            custom_code,
+            message: log.clone(),
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::raydium;
+
+    #[test]
+    fn custom_instruction_error_is_decoded() {
+        // InstructionError(2, Custom(30))：bincode 枚举序号为 u32 小端
+        let err = TransactionError {
+            err: vec![8, 0, 0, 0, 2, 25, 0, 0, 0, 30, 0, 0, 0],
+        };
+        let failure = decode_instruction_error(&err).unwrap();
+        assert_eq!(failure.instruction_index, 2);
+        assert_eq!(failure.custom_code, Some(30));
+        assert_eq!(failure.kind, "Custom");
+        assert_eq!(
+            raydium::amm_error_name(failure.custom_code.unwrap()),
+            Some("ExceededSlippage")
+        );
+    }
+
+    #[test]
+    fn builtin_instruction_error_uses_variant_name() {
+        // InstructionError(0, InvalidAccountData)
+        let err = TransactionError {
+            err: vec![8, 0, 0, 0, 0, 3, 0, 0, 0],
+        };
+        let failure = decode_instruction_error(&err).unwrap();
+        assert_eq!(failure.custom_code, None);
+        assert_eq!(failure.kind, "InvalidAccountData");
+    }
+
+    #[test]
+    fn transaction_level_error_is_ignored() {
+        // TransactionError::InsufficientFundsForFee（序号 4）
+        let err = TransactionError {
+            err: vec![4, 0, 0, 0],
+        };
+        assert_eq!(decode_instruction_error(&err), None);
+        assert_eq!(
+            decode_instruction_error(&TransactionError { err: vec![8] }),
+            None
+        );
+    }
+
+    #[test]
+    fn first_failed_log_is_innermost_program() {
+        let logs: Vec<String> = [
+            "Program JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4 invoke [1]",
+            "Program 675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8 invoke [2]",
+            "Program log: Error: exceeds desired slippage limit",
+            "Program 675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8 failed: custom program error: 0x1e",
+            "Program JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4 failed: custom program error: 0x1771",
+        ]
+        .iter()
+        .map(|l| l.to_string())
+        .collect();
+
+        let failed = first_failed_log(&logs).unwrap();
+        assert_eq!(failed.program, crate::RAYDIUM);
+        assert_eq!(failed.custom_code, Some(0x1e));
+        assert_eq!(raydium::amm_error_name(0x1e), Some("ExceededSlippage"));
+        assert_eq!(failed.message, logs[3]);
+    }
+
+    #[test]
+    fn non_custom_failure_has_no_code() {
+        let logs = vec![
+            "Program 675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8 failed: invalid account data for instruction"
+                .to_string(),
+        ];
+        let failed = first_failed_log(&logs).unwrap();
+        assert_eq!(failed.custom_code, None);
+        assert_eq!(first_failed_log(&[]), None);
+    }
+}
@@ -0,0 +1,56 @@
+// 主网录制区块的 golden 测试：对 tests/fixtures/<case>.bin 执行与 map_ray_swap 相同的提取，
+// 与 tests/golden/<case>.json 逐字节比较。待录制的场景与录制方法见 tests/fixtures/README.md，
+// 录制之前这些场景只由 tests/synthetic.rs 的合成区块覆盖。
+//
+// 需要 cli feature（serde 派生）：cargo test --features cli --test fixtures
+// 重新生成 golden：UPDATE_GOLDEN=1 cargo test --features cli --test fixtures
+
+use sol_ray_swap::{read_blocks, Replay};
+use std::path::{Path, PathBuf};
+
+fn root() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests")
+}
+
+// fixture 可以是单个交易裁剪出的区块，也可以是连续多个区块；按文件内顺序回放
+fn run_fixture(path: &Path) -> String {
+    let bytes = std::fs::read(path).unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+    let blocks = read_blocks(&bytes).unwrap_or_else(|e| panic!("{}: {}", path.display(), e));
+
+    let mut replay = Replay::new();
+    let events: Vec<_> = blocks
+        .iter()
+        .flat_map(|block| replay.process(block).events)
+        .collect();
+    serde_json::to_string_pretty(&events).unwrap() + "\n"
+}
+
+#[test]
+fn golden_trade_events() {
+    let update = std::env::var_os("UPDATE_GOLDEN").is_some();
+    let mut fixtures: Vec<PathBuf> = std::fs::read_dir(root().join("fixtures"))
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "bin"))
+        .collect();
+    fixtures.sort();
+
+    let mut mismatches: Vec<String> = Vec::new();
+    for fixture in &fixtures {
+        let case = fixture.file_stem().unwrap().to_string_lossy().to_string();
+        let golden = root().join("golden").join(format!("{}.json", case));
+        let actual = run_fixture(fixture);
+
+        if update {
+            std::fs::write(&golden, &actual).unwrap();
+            continue;
+        }
+        match std::fs::read_to_string(&golden) {
+            Ok(expected) if expected == actual => {}
+            Ok(_) => mismatches.push(format!("{}: output differs from golden", case)),
+            Err(_) => mismatches.push(format!("{}: no golden, run with UPDATE_GOLDEN=1", case)),
+        }
+    }
+
+    assert!(mismatches.is_empty(), "{}", mismatches.join("\n"));
+}
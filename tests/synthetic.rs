@@ -0,0 +1,658 @@
+// 合成场景的 golden 测试：用 src/testing.rs 的构造器在测试内构造区块，回放后与
+// tests/golden/synthetic/<case>.json 逐字节比较。
+//
+// 这些区块不是主网录制的：程序地址与 mint 为主网地址，池子、金库、用户账户与签名由种子生成，
+// 账户布局、日志与余额按主网交易的结构手工构造。它们只能防止提取逻辑回归，不能证明与主网数据一致；
+// 主网录制的区块由 tests/fixtures.rs 覆盖。
+//
+// cargo test --features cli,test-support --test synthetic
+// 重新生成 golden：UPDATE_GOLDEN=1 cargo test --features cli,test-support --test synthetic
+
+use sol_ray_swap::raydium::RayLog;
+use sol_ray_swap::testing::{
+    pubkey, ray_log, signature, swap_base_in_data, swap_base_out_data, BlockBuilder,
+    SwapAccountsSpec, TransactionBuilder,
+};
+use sol_ray_swap::{Replay, RAYDIUM};
+use std::path::Path;
+
+const JUPITER: &str = "JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4";
+const TOKEN_PROGRAM: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+const SYSTEM_PROGRAM: &str = "11111111111111111111111111111111";
+const COMPUTE_BUDGET: &str = "ComputeBudget111111111111111111111111111111";
+const AMM_AUTHORITY: &str = "5Q544fKrFoe6tsEbD7S8EmxGTJYAKtTVhAW5Q5pge4j1";
+const WSOL: &str = "So11111111111111111111111111111111111111112";
+const USDC: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+const RAY: &str = "4k3Dyjzvzp8eMZWUXbBCjEvwSkkk59S5iCNLY3QrkX6R";
+const JITO_TIP_ACCOUNT: &str = "96gYZGLnJYVFmbjzopPSU6QiEV5fGqZNyN9nmNhvrZU5";
+
+const SLOT: u64 = 312_345_678;
+
+// 合成池子：coin / pc 两个金库，owner 均为 AMM v4 共用的 amm_authority
+struct Pool {
+    amm: String,
+    coin_vault: String,
+    pc_vault: String,
+    coin_mint: &'static str,
+    pc_mint: &'static str,
+    coin_decimals: u32,
+    pc_decimals: u32,
+}
+
+fn sol_usdc() -> Pool {
+    Pool {
+        amm: pubkey(30),
+        coin_vault: pubkey(31),
+        pc_vault: pubkey(32),
+        coin_mint: WSOL,
+        pc_mint: USDC,
+        coin_decimals: 9,
+        pc_decimals: 6,
+    }
+}
+
+fn ray_usdc() -> Pool {
+    Pool {
+        amm: pubkey(40),
+        coin_vault: pubkey(41),
+        pc_vault: pubkey(42),
+        coin_mint: RAY,
+        pc_mint: USDC,
+        coin_decimals: 6,
+        pc_decimals: 6,
+    }
+}
+
+impl Pool {
+    fn swap_accounts(&self, source: &str, destination: &str, owner: &str) -> Vec<String> {
+        SwapAccountsSpec {
+            amm: &self.amm,
+            amm_authority: AMM_AUTHORITY,
+            pool_coin_token_account: &self.coin_vault,
+            pool_pc_token_account: &self.pc_vault,
+            user_source: source,
+            user_destination: destination,
+            user_owner: owner,
+        }
+        .to_accounts()
+    }
+
+    // 金库余额：coin / pc 各自的 (pre, post)
+    fn vaults(
+        &self,
+        tx: TransactionBuilder,
+        coin: (u64, u64),
+        pc: (u64, u64),
+    ) -> TransactionBuilder {
+        tx.token_balance(
+            &self.coin_vault,
+            self.coin_mint,
+            AMM_AUTHORITY,
+            self.coin_decimals,
+            Some(coin.0),
+            Some(coin.1),
+        )
+        .token_balance(
+            &self.pc_vault,
+            self.pc_mint,
+            AMM_AUTHORITY,
+            self.pc_decimals,
+            Some(pc.0),
+            Some(pc.1),
+        )
+    }
+}
+
+fn set_compute_unit_limit(units: u32) -> Vec<u8> {
+    let mut data = vec![2];
+    data.extend_from_slice(&units.to_le_bytes());
+    data
+}
+
+fn set_compute_unit_price(micro_lamports: u64) -> Vec<u8> {
+    let mut data = vec![3];
+    data.extend_from_slice(&micro_lamports.to_le_bytes());
+    data
+}
+
+// 顶层两条 ComputeBudget 指令（序号 0、1）
+fn with_budget(tx: TransactionBuilder) -> TransactionBuilder {
+    tx.instruction(COMPUTE_BUDGET, &[], set_compute_unit_limit(300_000))
+        .instruction(COMPUTE_BUDGET, &[], set_compute_unit_price(50_000))
+        .log(&format!("Program {} invoke [1]", COMPUTE_BUDGET))
+        .log(&format!("Program {} success", COMPUTE_BUDGET))
+        .log(&format!("Program {} invoke [1]", COMPUTE_BUDGET))
+        .log(&format!("Program {} success", COMPUTE_BUDGET))
+}
+
+// direction：1 为 coin → pc，2 为 pc → coin
+fn base_in_log(
+    direction: u64,
+    amount_in: u64,
+    minimum_out: u64,
+    out_amount: u64,
+    reserves: (u64, u64),
+) -> String {
+    ray_log(RayLog::SwapBaseIn {
+        amount_in,
+        minimum_out,
+        direction,
+        user_source: amount_in,
+        pool_coin: reserves.0,
+        pool_pc: reserves.1,
+        out_amount,
+    })
+}
+
+fn invoke(program: &str, depth: u32) -> String {
+    format!("Program {} invoke [{}]", program, depth)
+}
+
+fn consumed(program: &str, units: u64, limit: u64) -> String {
+    format!(
+        "Program {} consumed {} of {} compute units",
+        program, units, limit
+    )
+}
+
+fn success(program: &str) -> String {
+    format!("Program {} success", program)
+}
+
+// 直接调用 AMM v4：卖出 150 USDC 换 WSOL（顶层指令 2）
+fn amm_v4_direct_swap() -> BlockBuilder {
+    let pool = sol_usdc();
+    let trader = pubkey(100);
+    let (user_wsol, user_usdc) = (pubkey(101), pubkey(102));
+    let tx = with_budget(TransactionBuilder::new(&signature(1), &trader))
+        .instruction(
+            RAYDIUM,
+            &pool.swap_accounts(&user_usdc, &user_wsol, &trader),
+            swap_base_in_data(150_000_000, 995_000_000),
+        )
+        .log(&invoke(RAYDIUM, 1))
+        .log(&base_in_log(
+            2,
+            150_000_000,
+            995_000_000,
+            1_000_000_000,
+            (80_000_000_000_000, 12_000_000_000_000),
+        ))
+        .log(&invoke(TOKEN_PROGRAM, 2))
+        .log("Program log: Instruction: Transfer")
+        .log(&consumed(TOKEN_PROGRAM, 4_736, 268_000))
+        .log(&success(TOKEN_PROGRAM))
+        .log(&invoke(TOKEN_PROGRAM, 2))
+        .log("Program log: Instruction: Transfer")
+        .log(&consumed(TOKEN_PROGRAM, 4_645, 259_000))
+        .log(&success(TOKEN_PROGRAM))
+        .log(&consumed(RAYDIUM, 31_201, 299_700))
+        .log(&success(RAYDIUM))
+        .inner_instruction(
+            2,
+            TOKEN_PROGRAM,
+            &[user_usdc.clone(), pool.pc_vault.clone(), trader.clone()],
+            vec![3],
+        )
+        .inner_instruction(
+            2,
+            TOKEN_PROGRAM,
+            &[
+                pool.coin_vault.clone(),
+                user_wsol.clone(),
+                AMM_AUTHORITY.to_string(),
+            ],
+            vec![3],
+        )
+        .token_balance(
+            &user_usdc,
+            USDC,
+            &trader,
+            6,
+            Some(500_000_000),
+            Some(350_000_000),
+        )
+        .token_balance(
+            &user_wsol,
+            WSOL,
+            &trader,
+            9,
+            Some(2_000_000_000),
+            Some(3_000_000_000),
+        )
+        .lamports(&trader, 5_000_000_000, 4_999_980_000)
+        .fee(20_000)
+        .compute_units_consumed(31_501);
+    let tx = pool.vaults(
+        tx,
+        (80_000_000_000_000, 79_999_000_000_000),
+        (12_000_000_000_000, 12_000_150_000_000),
+    );
+    BlockBuilder::new(SLOT).transaction(tx)
+}
+
+// 经 Jupiter CPI 调用的 swap base out：最多付 1 WSOL，买入精确 140 USDC
+fn cpi_swap_via_aggregator() -> BlockBuilder {
+    let pool = sol_usdc();
+    let trader = pubkey(110);
+    let (user_wsol, user_usdc) = (pubkey(111), pubkey(112));
+    let tx = with_budget(TransactionBuilder::new(&signature(2), &trader))
+        .instruction(
+            JUPITER,
+            &[trader.clone(), user_wsol.clone(), user_usdc.clone()],
+            vec![229, 23, 203, 151, 122, 227, 173, 42],
+        )
+        .inner_instruction(
+            2,
+            RAYDIUM,
+            &pool.swap_accounts(&user_wsol, &user_usdc, &trader),
+            swap_base_out_data(1_000_000_000, 140_000_000),
+        )
+        .log(&invoke(JUPITER, 1))
+        .log("Program log: Instruction: Route")
+        .log(&invoke(RAYDIUM, 2))
+        .log(&ray_log(RayLog::SwapBaseOut {
+            max_in: 1_000_000_000,
+            amount_out: 140_000_000,
+            direction: 1,
+            user_source: 3_000_000_000,
+            pool_coin: 80_000_000_000_000,
+            pool_pc: 12_000_000_000_000,
+            deduct_in: 935_000_000,
+        }))
+        .log(&consumed(RAYDIUM, 29_874, 274_100))
+        .log(&success(RAYDIUM))
+        .log("Program data: QMbN6CYIceIFAAAAAAAAAA==")
+        .log(&consumed(JUPITER, 55_412, 299_700))
+        .log(&success(JUPITER))
+        .token_balance(
+            &user_wsol,
+            WSOL,
+            &trader,
+            9,
+            Some(3_000_000_000),
+            Some(2_065_000_000),
+        )
+        .token_balance(&user_usdc, USDC, &trader, 6, Some(0), Some(140_000_000))
+        .lamports(&trader, 2_000_000_000, 1_999_980_000)
+        .fee(20_000)
+        .compute_units_consumed(55_712);
+    let tx = pool.vaults(
+        tx,
+        (80_000_000_000_000, 80_000_935_000_000),
+        (12_000_000_000_000, 11_999_860_000_000),
+    );
+    BlockBuilder::new(SLOT).transaction(tx)
+}
+
+// 用 SOL 直接买 USDC：创建临时 WSOL 账户、swap、关闭账户。临时账户没有代币余额条目
+fn wsol_round_trip() -> BlockBuilder {
+    let pool = sol_usdc();
+    let trader = pubkey(120);
+    let (temp_wsol, user_usdc) = (pubkey(121), pubkey(122));
+    let tx = with_budget(TransactionBuilder::new(&signature(3), &trader))
+        .instruction(
+            SYSTEM_PROGRAM,
+            &[trader.clone(), temp_wsol.clone()],
+            vec![0],
+        )
+        .instruction(
+            TOKEN_PROGRAM,
+            &[temp_wsol.clone(), WSOL.to_string(), trader.clone()],
+            vec![18],
+        )
+        .instruction(
+            RAYDIUM,
+            &pool.swap_accounts(&temp_wsol, &user_usdc, &trader),
+            swap_base_in_data(500_000_000, 70_000_000),
+        )
+        .instruction(
+            TOKEN_PROGRAM,
+            &[temp_wsol.clone(), trader.clone(), trader.clone()],
+            vec![9],
+        )
+        .log(&invoke(SYSTEM_PROGRAM, 1))
+        .log(&success(SYSTEM_PROGRAM))
+        .log(&invoke(TOKEN_PROGRAM, 1))
+        .log("Program log: Instruction: InitializeAccount3")
+        .log(&consumed(TOKEN_PROGRAM, 3_158, 300_000))
+        .log(&success(TOKEN_PROGRAM))
+        .log(&invoke(RAYDIUM, 1))
+        .log(&base_in_log(
+            1,
+            500_000_000,
+            70_000_000,
+            74_950_000,
+            (80_000_000_000_000, 12_000_000_000_000),
+        ))
+        .log(&consumed(RAYDIUM, 28_507, 296_842))
+        .log(&success(RAYDIUM))
+        .log(&invoke(TOKEN_PROGRAM, 1))
+        .log("Program log: Instruction: CloseAccount")
+        .log(&consumed(TOKEN_PROGRAM, 2_915, 268_335))
+        .log(&success(TOKEN_PROGRAM))
+        .token_balance(
+            &user_usdc,
+            USDC,
+            &trader,
+            6,
+            Some(10_000_000),
+            Some(84_950_000),
+        )
+        .lamports(&trader, 1_000_000_000, 499_980_000)
+        .fee(20_000)
+        .compute_units_consumed(34_880);
+    let tx = pool.vaults(
+        tx,
+        (80_000_000_000_000, 80_000_500_000_000),
+        (12_000_000_000_000, 11_999_925_050_000),
+    );
+    BlockBuilder::new(SLOT).transaction(tx)
+}
+
+// Jupiter 路由经过两个池子：RAY → USDC → WSOL，两个池子共用同一个 amm_authority
+fn multi_hop() -> BlockBuilder {
+    let (first, second) = (ray_usdc(), sol_usdc());
+    let trader = pubkey(130);
+    let (user_ray, user_usdc, user_wsol) = (pubkey(131), pubkey(132), pubkey(133));
+    let tx = with_budget(TransactionBuilder::new(&signature(4), &trader))
+        .instruction(
+            JUPITER,
+            &[trader.clone(), user_ray.clone(), user_wsol.clone()],
+            vec![229, 23, 203, 151, 122, 227, 173, 42],
+        )
+        .inner_instruction(
+            2,
+            RAYDIUM,
+            &first.swap_accounts(&user_ray, &user_usdc, &trader),
+            swap_base_in_data(50_000_000, 90_000_000),
+        )
+        .inner_instruction(
+            2,
+            RAYDIUM,
+            &second.swap_accounts(&user_usdc, &user_wsol, &trader),
+            swap_base_in_data(92_500_000, 600_000_000),
+        )
+        .log(&invoke(JUPITER, 1))
+        .log("Program log: Instruction: Route")
+        .log(&invoke(RAYDIUM, 2))
+        .log(&base_in_log(
+            1,
+            50_000_000,
+            90_000_000,
+            92_500_000,
+            (1_000_000_000_000, 1_850_000_000_000),
+        ))
+        .log(&consumed(RAYDIUM, 30_112, 280_000))
+        .log(&success(RAYDIUM))
+        .log(&invoke(RAYDIUM, 2))
+        .log(&base_in_log(
+            2,
+            92_500_000,
+            600_000_000,
+            616_600_000,
+            (80_000_000_000_000, 12_000_000_000_000),
+        ))
+        .log(&consumed(RAYDIUM, 29_540, 245_000))
+        .log(&success(RAYDIUM))
+        .log(&consumed(JUPITER, 88_301, 299_700))
+        .log(&success(JUPITER))
+        .token_balance(
+            &user_ray,
+            RAY,
+            &trader,
+            6,
+            Some(80_000_000),
+            Some(30_000_000),
+        )
+        .token_balance(
+            &user_usdc,
+            USDC,
+            &trader,
+            6,
+            Some(1_000_000),
+            Some(1_000_000),
+        )
+        .token_balance(&user_wsol, WSOL, &trader, 9, Some(0), Some(616_600_000))
+        .lamports(&trader, 3_000_000_000, 2_999_980_000)
+        .fee(20_000)
+        .compute_units_consumed(88_601);
+    let tx = first.vaults(
+        tx,
+        (1_000_000_000_000, 1_000_050_000_000),
+        (1_850_000_000_000, 1_849_907_500_000),
+    );
+    let tx = second.vaults(
+        tx,
+        (80_000_000_000_000, 79_999_383_400_000),
+        (12_000_000_000_000, 12_000_092_500_000),
+    );
+    BlockBuilder::new(SLOT).transaction(tx)
+}
+
+// 超出滑点回滚的 swap：Raydium 抛出 0x1e（ExceededSlippage），整笔交易失败，余额不变
+fn failed_swap() -> BlockBuilder {
+    let pool = sol_usdc();
+    let trader = pubkey(140);
+    let (user_wsol, user_usdc) = (pubkey(141), pubkey(142));
+    let tx = with_budget(TransactionBuilder::new(&signature(5), &trader))
+        .instruction(
+            JUPITER,
+            &[trader.clone(), user_wsol.clone(), user_usdc.clone()],
+            vec![229, 23, 203, 151, 122, 227, 173, 42],
+        )
+        .inner_instruction(
+            2,
+            RAYDIUM,
+            &pool.swap_accounts(&user_wsol, &user_usdc, &trader),
+            swap_base_in_data(1_000_000_000, 160_000_000),
+        )
+        .log(&invoke(JUPITER, 1))
+        .log("Program log: Instruction: Route")
+        .log(&invoke(RAYDIUM, 2))
+        .log("Program log: Error: exceeds desired slippage limit")
+        .log(&consumed(RAYDIUM, 17_960, 274_100))
+        .log(&format!(
+            "Program {} failed: custom program error: 0x1e",
+            RAYDIUM
+        ))
+        .log(&consumed(JUPITER, 43_302, 299_700))
+        .log(&format!(
+            "Program {} failed: custom program error: 0x1e",
+            JUPITER
+        ))
+        .token_balance(
+            &user_wsol,
+            WSOL,
+            &trader,
+            9,
+            Some(3_000_000_000),
+            Some(3_000_000_000),
+        )
+        .token_balance(&user_usdc, USDC, &trader, 6, Some(0), Some(0))
+        .lamports(&trader, 2_000_000_000, 1_999_980_000)
+        .fee(20_000)
+        .compute_units_consumed(43_602)
+        .failed();
+    let tx = pool.vaults(
+        tx,
+        (80_000_000_000_000, 80_000_000_000_000),
+        (12_000_000_000_000, 12_000_000_000_000),
+    );
+    BlockBuilder::new(SLOT).transaction(tx)
+}
+
+// v0 交易：AMM、金库与用户 USDC 账户经 ALT 加载，amm_authority 为只读加载
+fn v0_with_alts() -> BlockBuilder {
+    let pool = sol_usdc();
+    let trader = pubkey(150);
+    let (user_wsol, user_usdc) = (pubkey(151), pubkey(152));
+    let tx = with_budget(TransactionBuilder::new(&signature(6), &trader))
+        .instruction(
+            JUPITER,
+            &[trader.clone(), user_wsol.clone(), user_usdc.clone()],
+            vec![229, 23, 203, 151, 122, 227, 173, 42],
+        )
+        .inner_instruction(
+            2,
+            RAYDIUM,
+            &pool.swap_accounts(&user_wsol, &user_usdc, &trader),
+            swap_base_in_data(2_000_000_000, 280_000_000),
+        )
+        .lookup_writable(&pool.amm)
+        .lookup_writable(&pool.coin_vault)
+        .lookup_writable(&pool.pc_vault)
+        .lookup_writable(&user_usdc)
+        .lookup_readonly(AMM_AUTHORITY)
+        .log(&invoke(JUPITER, 1))
+        .log("Program log: Instruction: Route")
+        .log(&invoke(RAYDIUM, 2))
+        .log(&base_in_log(
+            1,
+            2_000_000_000,
+            280_000_000,
+            299_250_000,
+            (80_000_000_000_000, 12_000_000_000_000),
+        ))
+        .log(&consumed(RAYDIUM, 31_020, 274_100))
+        .log(&success(RAYDIUM))
+        .log(&consumed(JUPITER, 57_880, 299_700))
+        .log(&success(JUPITER))
+        .token_balance(
+            &user_wsol,
+            WSOL,
+            &trader,
+            9,
+            Some(5_000_000_000),
+            Some(3_000_000_000),
+        )
+        .token_balance(
+            &user_usdc,
+            USDC,
+            &trader,
+            6,
+            Some(1_000_000),
+            Some(300_250_000),
+        )
+        .lamports(&trader, 2_000_000_000, 1_999_980_000)
+        .fee(20_000)
+        .compute_units_consumed(58_180);
+    let tx = pool.vaults(
+        tx,
+        (80_000_000_000_000, 80_002_000_000_000),
+        (12_000_000_000_000, 11_999_700_750_000),
+    );
+    BlockBuilder::new(SLOT).transaction(tx)
+}
+
+// bundle 中的 swap 交易：swap 之后向 Jito tip 账户转账 0.0001 SOL（顶层指令 3）
+fn jito_bundle_tip() -> BlockBuilder {
+    let pool = sol_usdc();
+    let trader = pubkey(170);
+    let (user_wsol, user_usdc) = (pubkey(171), pubkey(172));
+    let mut transfer = 2u32.to_le_bytes().to_vec();
+    transfer.extend_from_slice(&100_000u64.to_le_bytes());
+    let tx = with_budget(TransactionBuilder::new(&signature(7), &trader))
+        .instruction(
+            RAYDIUM,
+            &pool.swap_accounts(&user_wsol, &user_usdc, &trader),
+            swap_base_in_data(2_000_000_000, 295_000_000),
+        )
+        .instruction(
+            SYSTEM_PROGRAM,
+            &[trader.clone(), JITO_TIP_ACCOUNT.to_string()],
+            transfer,
+        )
+        .log(&invoke(RAYDIUM, 1))
+        .log(&base_in_log(
+            1,
+            2_000_000_000,
+            295_000_000,
+            299_250_000,
+            (80_000_000_000_000, 12_000_000_000_000),
+        ))
+        .log(&invoke(TOKEN_PROGRAM, 2))
+        .log("Program log: Instruction: Transfer")
+        .log(&consumed(TOKEN_PROGRAM, 4_736, 268_000))
+        .log(&success(TOKEN_PROGRAM))
+        .log(&invoke(TOKEN_PROGRAM, 2))
+        .log("Program log: Instruction: Transfer")
+        .log(&consumed(TOKEN_PROGRAM, 4_645, 259_000))
+        .log(&success(TOKEN_PROGRAM))
+        .log(&consumed(RAYDIUM, 30_884, 299_700))
+        .log(&success(RAYDIUM))
+        .log(&invoke(SYSTEM_PROGRAM, 1))
+        .log(&success(SYSTEM_PROGRAM))
+        .inner_instruction(
+            2,
+            TOKEN_PROGRAM,
+            &[user_wsol.clone(), pool.coin_vault.clone(), trader.clone()],
+            vec![3],
+        )
+        .inner_instruction(
+            2,
+            TOKEN_PROGRAM,
+            &[
+                pool.pc_vault.clone(),
+                user_usdc.clone(),
+                AMM_AUTHORITY.to_string(),
+            ],
+            vec![3],
+        )
+        .token_balance(
+            &user_wsol,
+            WSOL,
+            &trader,
+            9,
+            Some(5_000_000_000),
+            Some(3_000_000_000),
+        )
+        .token_balance(&user_usdc, USDC, &trader, 6, Some(0), Some(299_250_000))
+        .lamports(&trader, 5_000_000_000, 4_999_880_000)
+        .lamports(JITO_TIP_ACCOUNT, 1_000_000_000, 1_000_100_000)
+        .fee(20_000)
+        .compute_units_consumed(31_334);
+    let tx = pool.vaults(
+        tx,
+        (80_000_000_000_000, 80_002_000_000_000),
+        (12_000_000_000_000, 11_999_700_750_000),
+    );
+    BlockBuilder::new(SLOT).transaction(tx)
+}
+
+type Case = fn() -> BlockBuilder;
+
+const CASES: &[(&str, Case)] = &[
+    ("amm_v4_direct_swap", amm_v4_direct_swap),
+    ("cpi_swap_via_aggregator", cpi_swap_via_aggregator),
+    ("wsol_round_trip", wsol_round_trip),
+    ("multi_hop", multi_hop),
+    ("failed_swap", failed_swap),
+    ("v0_with_alts", v0_with_alts),
+    ("jito_bundle_tip", jito_bundle_tip),
+];
+
+#[test]
+fn golden_synthetic_trade_events() {
+    let update = std::env::var_os("UPDATE_GOLDEN").is_some();
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden/synthetic");
+
+    let mut mismatches: Vec<String> = Vec::new();
+    for (case, build) in CASES {
+        let events = Replay::new().process(&build().build()).events;
+        let actual = serde_json::to_string_pretty(&events).unwrap() + "\n";
+        let golden = dir.join(format!("{}.json", case));
+
+        if update {
+            std::fs::write(&golden, &actual).unwrap();
+            continue;
+        }
+        match std::fs::read_to_string(&golden) {
+            Ok(expected) if expected == actual => {}
+            Ok(_) => mismatches.push(format!("{}: output differs from golden", case)),
+            Err(_) => mismatches.push(format!("{}: no golden, run with UPDATE_GOLDEN=1", case)),
+        }
+    }
+
+    assert!(mismatches.is_empty(), "{}", mismatches.join("\n"));
+}